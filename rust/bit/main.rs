@@ -1,41 +1,434 @@
 use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use boot::*;
 
+mod repl;
+
+/// Default stack size for the thread running the program, since nested bit
+/// function calls are executed recursively by the runtime.
+const DEFAULT_STACK_SIZE: usize = 1024 * MIB;
+
+const MIB: usize = 1024 * 1024;
+
+const USAGE: &str = "\
+Usage: bit [COMMAND] [OPTIONS] FILES...
+
+Commands:
+    run       run the program with the interpreter (default)
+    build     compile the program to a native executable
+    check     parse the program without running it
+    tokens    print the tokens for each source file
+    ast       print the parsed program tree
+    ir        print the generated code
+    repl      start an interactive session
+
+Options:
+    -o PATH   output path for `build`
+    --format=text|dot|html
+              output format for `ast`
+    --show-evals
+              include the evals that changed each node in `ast`
+    --error-format=text|json
+              format for errors written to stderr
+    -W error  treat warnings as errors
+    --max-errors N
+              stop reporting errors after the first N
+    --jobs N  number of threads used to parse the program
+    --max-steps N
+              fail if parsing takes more than N steps
+    --stack-size N
+              stack size in MiB for `run` and `repl` (default 1024), which
+              limits the depth of nested function calls
+    --trace-eval
+              log each parser eval to stderr
+    --step    pause after each parser eval (implies `--trace-eval`)
+    -h, --help
+              print this help
+    --version print the version
+";
+
 fn main() {
-	if let Err(err) = run() {
-		eprintln!("\n{err}\n");
-		std::process::exit(1);
+	let args = Args::parse();
+	let format = args.as_ref().map(|x| x.error_format).unwrap_or_default();
+	let stack_size = args.as_ref().map(|x| x.stack_size).unwrap_or(DEFAULT_STACK_SIZE);
+	let main = std::thread::Builder::new().stack_size(stack_size).spawn(move || {
+		let result = args.and_then(|args| match args.command {
+			Some(command) => run(command, args),
+			None => Ok(()),
+		});
+		if let Err(err) = result {
+			report(&err, format);
+			std::process::exit(1);
+		}
+	});
+	match main {
+		Ok(main) => main.join().unwrap(),
+		Err(err) => {
+			let err = err!(
+				"cannot start the main thread with a stack of {} MiB: {err}",
+				stack_size / MIB
+			);
+			report(&err, format);
+			std::process::exit(1);
+		}
 	}
 }
 
-fn run() -> Result<()> {
-	init_core();
+/// Parsed command line arguments.
+struct Args {
+	/// Command to run, if any (e.g. not for `--version`).
+	command: Option<Command>,
+	files: Vec<String>,
+	output: Option<PathBuf>,
+	tree_format: TreeFormat,
+	show_evals: bool,
+	error_format: ErrorFormat,
+	warnings_as_errors: bool,
+	max_errors: Option<usize>,
+	jobs: usize,
+	max_steps: usize,
+	/// Stack size in bytes for the thread running the program.
+	stack_size: usize,
+	trace_eval: bool,
+	step: bool,
+}
 
-	let sources = SourceMap::new(".")?;
-	let mut input = HashSet::new();
+impl Args {
+	fn parse() -> Result<Args> {
+		Self::parse_from(std::env::args().skip(1))
+	}
+
+	/// Parses the arguments following the program name.
+	fn parse_from<T: IntoIterator<Item = String>>(args: T) -> Result<Args> {
+		let mut args = args.into_iter().peekable();
+		let mut output = Args {
+			command: None,
+			files: Vec::new(),
+			output: None,
+			tree_format: TreeFormat::Text,
+			show_evals: false,
+			error_format: ErrorFormat::Text,
+			warnings_as_errors: false,
+			max_errors: None,
+			jobs: 1,
+			max_steps: DEFAULT_MAX_STEPS,
+			stack_size: DEFAULT_STACK_SIZE,
+			trace_eval: false,
+			step: false,
+		};
+
+		let command = match args.peek().map(|x| x.as_str()) {
+			None | Some("-h" | "--help" | "help") => {
+				print!("{USAGE}");
+				return Ok(output);
+			}
+			Some("--version") => {
+				println!("bit {}", env!("CARGO_PKG_VERSION"));
+				return Ok(output);
+			}
+			Some(name) => match Command::parse(name) {
+				Some(cmd) => {
+					args.next();
+					cmd
+				}
+				// `bit FILES...` is a shortcut for `bit run FILES...`
+				None => Command::Run,
+			},
+		};
 
-	let mut options = Options::default();
-	for it in std::env::args().skip(1) {
-		if it == "--show-program" {
-			options.show_program = true;
-		} else if it == "--dump-code" {
-			options.dump_code = true;
-		} else if it == "--dump" {
-			options.show_program = true;
-			options.dump_code = true;
-		} else if it == "--compile" {
-			options.compile = true;
-		} else {
-			let src = sources.load_file(it)?;
-			input.insert(src);
+		while let Some(arg) = args.next() {
+			if arg == "-o" && command == Command::Build {
+				match args.next() {
+					Some(path) => output.output = Some(PathBuf::from(path)),
+					None => raise!("missing path for `-o`\n\n{USAGE}"),
+				}
+			} else if let (Some(format), Command::Ast) = (arg.strip_prefix("--format="), command) {
+				output.tree_format = match format {
+					"text" => TreeFormat::Text,
+					"dot" => TreeFormat::Dot,
+					"html" => TreeFormat::Html,
+					_ => raise!("invalid format `{format}`, expected `text`, `dot`, or `html`"),
+				};
+			} else if arg == "--show-evals" && command == Command::Ast {
+				output.show_evals = true;
+			} else if let Some(format) = arg.strip_prefix("--error-format=") {
+				output.error_format = match format {
+					"text" => ErrorFormat::Text,
+					"json" => ErrorFormat::Json,
+					_ => raise!("invalid error format `{format}`, expected `text` or `json`"),
+				};
+			} else if arg == "-W" {
+				match args.next().as_deref() {
+					Some("error") => output.warnings_as_errors = true,
+					Some(other) => raise!("invalid value `{other}` for `-W`, expected `error`"),
+					None => raise!("missing value for `-W`\n\n{USAGE}"),
+				}
+			} else if arg == "--max-errors" {
+				output.max_errors = Some(parse_count("--max-errors", args.next())?);
+			} else if arg == "--jobs" {
+				output.jobs = parse_count("--jobs", args.next())?;
+			} else if arg == "--max-steps" {
+				output.max_steps = parse_count("--max-steps", args.next())?;
+			} else if arg == "--stack-size" {
+				let size = parse_count("--stack-size", args.next())?;
+				output.stack_size = match size.checked_mul(MIB) {
+					Some(size) => size,
+					None => raise!("invalid value `{size}` for `--stack-size`, the stack is too large"),
+				};
+			} else if arg == "--trace-eval" {
+				output.trace_eval = true;
+			} else if arg == "--step" {
+				output.step = true;
+			} else if arg.starts_with('-') {
+				raise!("invalid option `{arg}`\n\n{USAGE}");
+			} else if command == Command::Repl {
+				raise!("invalid argument for repl: {arg}\n\n{USAGE}");
+			} else {
+				output.files.push(arg);
+			}
 		}
+
+		if output.files.is_empty() && command != Command::Repl {
+			raise!("no input files\n\n{USAGE}");
+		}
+
+		output.command = Some(command);
+		Ok(output)
+	}
+}
+
+/// Output format for the program tree in `ast`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum TreeFormat {
+	Text,
+	Dot,
+	Html,
+}
+
+/// Parses the positive number for an option.
+fn parse_count(option: &str, value: Option<String>) -> Result<usize> {
+	let value = match value {
+		Some(value) => value,
+		None => raise!("missing value for `{option}`\n\n{USAGE}"),
+	};
+	match value.parse::<usize>() {
+		Ok(count) if count > 0 => Ok(count),
+		_ => raise!("invalid value `{value}` for `{option}`, expected a positive number"),
+	}
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Command {
+	Run,
+	Build,
+	Check,
+	Tokens,
+	Ast,
+	Ir,
+	Repl,
+}
+
+impl Command {
+	fn parse(name: &str) -> Option<Command> {
+		let cmd = match name {
+			"run" => Command::Run,
+			"build" => Command::Build,
+			"check" => Command::Check,
+			"tokens" => Command::Tokens,
+			"ast" => Command::Ast,
+			"ir" => Command::Ir,
+			"repl" => Command::Repl,
+			_ => return None,
+		};
+		Some(cmd)
+	}
+}
+
+fn run(command: Command, args: Args) -> Result<()> {
+	init_core();
+	Runtime::set_stack_size(args.stack_size);
+	Queue::set_jobs(args.jobs);
+	Queue::set_max_steps(args.max_steps);
+	Trace::set_eval(args.trace_eval);
+	Trace::set_step(args.step);
+	Trace::set_record(args.show_evals);
+
+	let mut session = Session::new();
+	session.set_error_format(args.error_format);
+	session.set_warnings_as_errors(args.warnings_as_errors);
+	session.set_max_errors(args.max_errors);
+
+	let sources = Arc::new(SourceMap::new(".")?);
+	session.set_sources(sources.clone());
+	if command == Command::Repl {
+		return repl::run(&sources, session, args.error_format);
+	}
+
+	let mut input = HashSet::new();
+	for it in args.files.iter() {
+		let src = sources.load_file(it)?;
+		input.insert(src);
 	}
 
 	let mut input = input.into_iter().collect::<Vec<_>>();
 	input.sort();
 
-	execute(&input, options)?;
+	let result = run_command(command, &mut session, &input, &args);
+	session.report_summary();
+	result
+}
+
+fn run_command(command: Command, session: &mut Session, input: &[Source], args: &Args) -> Result<()> {
+	match command {
+		Command::Run => {
+			let value = session.execute(input)?;
+			if !value.is::<()>() {
+				println!("\nanswer = {value}");
+			}
+		}
+		Command::Check => {
+			let program = session.load(input);
+			session.solve(program)?;
+		}
+		Command::Tokens => {
+			let lexer = core_lexer();
+			let mut out = Writer::stdout();
+			let mut errors = Vec::new();
+			for src in input.iter() {
+				let mut cursor = Cursor::new(*src);
+				let (tokens, err) = lexer.tokenize_with_errors(&mut cursor);
+				for token in tokens {
+					write!(out, "{}\t", token.span().location())?;
+					token.describe(&mut out)?;
+					writeln!(out)?;
+				}
+				errors.extend(err);
+			}
+
+			if let Some(errors) = errors.into_iter().reduce(Error::append) {
+				return Err(errors);
+			}
+		}
+		Command::Ast => {
+			let program = session.load(input);
+			let result = session.solve(program);
+
+			let mut out = Writer::stdout();
+			match args.tree_format {
+				TreeFormat::Text => program.write(&mut out)?,
+				TreeFormat::Dot => program.write_dot(&mut out)?,
+				TreeFormat::Html => program.write_html(&mut out)?,
+			}
+			writeln!(out)?;
+			result?;
+		}
+		Command::Ir => {
+			let program = session.load(input);
+			session.solve(program)?;
+			let code = session.compile(program)?;
+			println!("{code:#?}");
+		}
+		Command::Build => {
+			let program = session.load(input);
+			session.solve(program)?;
+			let code = session.compile(program)?;
+
+			let mut builder = clang::Builder::new();
+			let code = code.generate_c(&mut builder)?;
+			let mut runner = builder.build(code);
+			let (dir, exe) = runner.compile()?;
+
+			let output = args.output.clone().unwrap_or_else(|| default_output(input[0]));
+			if let Err(err) = std::fs::copy(dir.path().join(exe), &output) {
+				raise!("writing {}: {err}", output.display());
+			}
+		}
+		Command::Repl => unreachable!(),
+	}
 
 	Ok(())
 }
+
+/// Executable name for `build` without `-o`, based on the first source.
+fn default_output(src: Source) -> PathBuf {
+	let name = src.name();
+	let name = Path::new(name).file_stem().unwrap_or_default();
+	PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(args: &str) -> Result<Args> {
+		Args::parse_from(args.split_whitespace().map(String::from))
+	}
+
+	fn parse_err(args: &str) -> String {
+		match parse(args) {
+			Ok(..) => panic!("expected an error for `{args}`"),
+			Err(err) => err.to_string(),
+		}
+	}
+
+	#[test]
+	fn commands_and_files() -> Result<()> {
+		let args = parse("a.bit b.bit")?;
+		assert_eq!(args.command, Some(Command::Run));
+		assert_eq!(args.files, ["a.bit", "b.bit"]);
+
+		let args = parse("build -o out a.bit")?;
+		assert_eq!(args.command, Some(Command::Build));
+		assert_eq!(args.output, Some(PathBuf::from("out")));
+
+		assert_eq!(parse("--help")?.command, None);
+		assert_eq!(parse("repl")?.command, Some(Command::Repl));
+		Ok(())
+	}
+
+	#[test]
+	fn invalid_options() {
+		assert!(parse_err("run --frobnicate a.bit").contains("invalid option `--frobnicate`"));
+		assert!(parse_err("run -o out a.bit").contains("invalid option `-o`"));
+		assert!(parse_err("build a.bit -o").contains("missing path for `-o`"));
+		assert!(parse_err("repl a.bit").contains("invalid argument for repl: a.bit"));
+	}
+
+	#[test]
+	fn missing_files() {
+		assert!(parse_err("run").contains("no input files"));
+		assert!(parse_err("build -o out").contains("no input files"));
+	}
+
+	#[test]
+	fn counts() -> Result<()> {
+		let args = parse("--jobs 4 --max-errors 2 --stack-size 8 a.bit")?;
+		assert_eq!(args.jobs, 4);
+		assert_eq!(args.max_errors, Some(2));
+		assert_eq!(args.stack_size, 8 * MIB);
+
+		let args = parse("a.bit")?;
+		assert_eq!(
+			(args.jobs, args.max_errors, args.stack_size),
+			(1, None, DEFAULT_STACK_SIZE)
+		);
+
+		assert!(parse_err("--jobs 0 a.bit").contains("invalid value `0` for `--jobs`"));
+		assert!(parse_err("--jobs x a.bit").contains("invalid value `x` for `--jobs`"));
+		assert!(parse_err("--max-errors -1 a.bit").contains("invalid value `-1` for `--max-errors`"));
+		assert!(parse_err("a.bit --max-errors").contains("missing value for `--max-errors`"));
+		Ok(())
+	}
+
+	#[test]
+	fn warnings_as_errors() -> Result<()> {
+		assert!(parse("-W error a.bit")?.warnings_as_errors);
+		assert!(!parse("a.bit")?.warnings_as_errors);
+		assert!(parse_err("-W all a.bit").contains("invalid value `all` for `-W`"));
+		assert!(parse_err("a.bit -W").contains("missing value for `-W`"));
+		Ok(())
+	}
+}
@@ -0,0 +1,117 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use boot::*;
+
+/// Runs an interactive session reading entries from the standard input.
+///
+/// An entry is a single line, unless it opens an indented block (or a
+/// triple-quoted string), in which case it continues until an empty line.
+pub fn run(sources: &SourceMap, mut session: Session, error_format: ErrorFormat) -> Result<()> {
+	let interactive = std::io::stdin().is_terminal();
+	let mut lines = std::io::stdin().lock().lines();
+	let mut count = 0;
+	loop {
+		let entry = read_entry(&mut lines, interactive)?;
+		if entry.is_empty() {
+			if interactive {
+				println!();
+			}
+			return Ok(());
+		}
+
+		count += 1;
+		let src = sources.from_string(format!("repl-{count}"), entry);
+		match session.execute(&[src]) {
+			Ok(value) => {
+				if !value.is::<()>() {
+					println!("{value}");
+				}
+			}
+			Err(err) => report(&err, error_format),
+		}
+	}
+}
+
+/// Reads the next entry from the lines, which is empty at the end of the
+/// input. Prompts are only shown for an interactive session.
+fn read_entry<T: Iterator<Item = std::io::Result<String>>>(lines: &mut T, interactive: bool) -> Result<String> {
+	let mut entry = String::new();
+	loop {
+		if interactive {
+			print!("{}", if entry.is_empty() { ">>> " } else { "... " });
+			std::io::stdout().flush()?;
+		}
+
+		let line = match lines.next() {
+			Some(line) => line?,
+			None => break,
+		};
+
+		if line.trim().is_empty() && !in_string(&entry) {
+			if entry.is_empty() {
+				continue;
+			}
+			break;
+		}
+
+		entry.push_str(&line);
+		entry.push('\n');
+		if !is_open(&entry) {
+			break;
+		}
+	}
+	Ok(entry)
+}
+
+/// Returns true if the entry has opened a block or string, and so continues
+/// until an empty line.
+fn is_open(entry: &str) -> bool {
+	in_string(entry)
+		|| entry.lines().any(|line| {
+			// this is not exact for a `#` inside a string, but good enough
+			let line = line.split('#').next().unwrap();
+			line.trim_end().ends_with(':')
+		})
+}
+
+fn in_string(entry: &str) -> bool {
+	entry.matches("\"\"\"").count() % 2 == 1
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entries(input: &str) -> Vec<String> {
+		let mut lines = input.lines().map(|x| Ok(x.to_string()));
+		let mut output = Vec::new();
+		loop {
+			let entry = read_entry(&mut lines, false).unwrap();
+			if entry.is_empty() {
+				return output;
+			}
+			output.push(entry);
+		}
+	}
+
+	#[test]
+	fn single_line_entries() {
+		assert_eq!(entries("let x = 1\n\n\nprint x"), ["let x = 1\n", "print x\n"]);
+	}
+
+	#[test]
+	fn blocks_continue_until_an_empty_line() {
+		let input = "fn f(a):\n\tif a:\n\t\treturn 1 # done:\n\treturn 2\n\nf(true)";
+		let block = "fn f(a):\n\tif a:\n\t\treturn 1 # done:\n\treturn 2\n";
+		assert_eq!(entries(input), [block, "f(true)\n"]);
+
+		// a comment ending in `:` doesn't open a block
+		assert_eq!(entries("1 # a:\n2"), ["1 # a:\n", "2\n"]);
+	}
+
+	#[test]
+	fn strings_continue_across_empty_lines() {
+		let input = "print \"\"\"\na\n\nb\n\"\"\"\nprint 1";
+		assert_eq!(entries(input), ["print \"\"\"\na\n\nb\n\"\"\"\n", "print 1\n"]);
+	}
+}
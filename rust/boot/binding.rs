@@ -69,6 +69,13 @@ impl BindTable {
 		globals.push(eval);
 	}
 
+	/// Binds the eval only for the nodes within the span, for example a
+	/// variable name for the rest of its block.
+	///
+	/// Where spans for the same table overlap, the innermost span wins: the
+	/// nodes inside a narrower span are only given to the evals bound to it,
+	/// regardless of their precedence. This includes the evals added with
+	/// [`BindTable::add_eval`], which span the whole source.
 	pub fn set_span<T: Eval>(&self, span: Span, eval: T) {
 		let map = self.get_by_source(span.source());
 		let eval = Arena::get().store(eval);
@@ -125,9 +132,37 @@ impl Bind {
 		let sta_index = nodes.partition_point(|x| x.offset() < sta);
 		let end_index = nodes[sta_index..].partition_point(|x| x.offset() < end) + sta_index;
 
+		// nodes inside a narrower bind are left to it, so it can shadow
+		let inner = parent.inner_spans(self.span);
+		let is_shadowed = |x: &Node| {
+			let index = inner.partition_point(|(sta, _)| *sta <= x.offset());
+			index > 0 && x.offset() < inner[index - 1].1
+		};
+		let shadowed;
+		let nodes_in_span = if inner.is_empty() {
+			&nodes[sta_index..end_index]
+		} else {
+			let visible = nodes[sta_index..end_index].iter().copied();
+			shadowed = visible.filter(|x| !is_shadowed(x)).collect::<Vec<_>>();
+			&shadowed[..]
+		};
+
+		{
+			let log = parent.log.lock().unwrap();
+			let visible = |x: Node| sta <= x.offset() && x.offset() < end && !is_shadowed(&x);
+			Queue::get().check_repeat(self, &log, visible)?;
+		}
+
 		self.parent.add_done(self);
 
-		self.eval.execute(&nodes[sta_index..end_index])?;
+		if Trace::is_enabled() {
+			let state = Trace::before(self.eval, nodes_in_span);
+			let result = self.eval.execute(nodes_in_span);
+			Trace::after(self.eval, self.span, state, &result)?;
+			result?;
+		} else {
+			self.eval.execute(nodes_in_span)?;
+		}
 
 		let mut cur = sta_index;
 		for index in sta_index..end_index {
@@ -138,7 +173,8 @@ impl Bind {
 			}
 		}
 
-		nodes.truncate(cur);
+		// keep any nodes after the bind span
+		nodes.drain(cur..end_index);
 
 		Ok(())
 	}
@@ -146,6 +182,14 @@ impl Bind {
 
 impl Eq for Bind {}
 
+impl Hash for Bind {
+	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+		(self.eval as *const dyn Eval).cast::<()>().hash(state);
+		self.span.hash(state);
+		(self.parent as *const BindingMap).hash(state);
+	}
+}
+
 impl PartialEq for Bind {
 	fn eq(&self, other: &Self) -> bool {
 		self.eval as *const _ == other.eval as *const _
@@ -180,6 +224,24 @@ impl Bind {
 	pub fn overlaps(&self, sta: usize, end: usize) -> bool {
 		self.span.sta() < end && sta < self.span.end()
 	}
+
+	/// Describes the eval and span for the bind, as used in errors.
+	pub fn describe(&self) -> String {
+		format!("`{}` at {}", eval_name(self.eval), self.span)
+	}
+
+	/// Can both binds be executed concurrently? This requires the same
+	/// precedence, and either different sources or non-overlapping spans.
+	///
+	/// SAFETY: the queue workers rely on this to share binds between
+	/// threads, so evals must uphold the rule that same-precedence binds
+	/// with disjoint spans never touch the same parent node.
+	pub fn is_independent(&self, other: &Bind) -> bool {
+		if self.eval.precedence() != other.eval.precedence() {
+			return false;
+		}
+		self.span.source() != other.span.source() || !self.overlaps(other.span.sta(), other.span.end())
+	}
 }
 
 impl Debug for Bind {
@@ -191,8 +253,14 @@ impl Debug for Bind {
 }
 
 pub(crate) struct BindingMap {
+	binds: Mutex<Vec<Bind>>,
+	/// Distinct spans of the binds, sorted.
+	spans: Mutex<Vec<Span>>,
 	nodes: Mutex<Vec<Node>>,
 	new_nodes: Mutex<Vec<Node>>,
+	/// Every node added to the map, in order, used to detect binds that
+	/// are not making progress (see [`Queue::check_repeat`]).
+	log: Mutex<Vec<Node>>,
 	pending: Mutex<Vec<Bind>>,
 	complete: Mutex<Vec<Bind>>,
 	pending_reindex: AtomicBool,
@@ -242,6 +310,7 @@ impl BindingMap {
 	fn add_node(&self, node: Node) {
 		let mut nodes = self.new_nodes.lock().unwrap();
 		nodes.push(node);
+		self.log.lock().unwrap().push(node);
 
 		let span = node.span();
 		self.changed_sta.fetch_min(span.sta(), Order::Relaxed);
@@ -249,10 +318,36 @@ impl BindingMap {
 	}
 
 	fn add_bind(&self, bind: Bind) {
+		self.binds.lock().unwrap().push(bind);
+		{
+			let mut spans = self.spans.lock().unwrap();
+			if let Err(index) = spans.binary_search(&bind.span) {
+				spans.insert(index, bind.span);
+			}
+		}
 		let mut pending = self.pending.lock().unwrap();
 		pending.push(bind);
 	}
 
+	/// Returns the spans for binds strictly inside the given span.
+	/// Returns the ranges covered by the binds narrower than `span` and
+	/// inside it, as sorted and disjoint `(sta, end)` pairs.
+	fn inner_spans(&self, span: Span) -> Vec<(usize, usize)> {
+		let spans = self.spans.lock().unwrap();
+		let index = spans.partition_point(|x| x.sta() < span.sta());
+		let inner = spans[index..].iter().take_while(|x| x.sta() < span.end());
+		let inner = inner.filter(|x| **x != span && x.end() <= span.end());
+
+		let mut ranges: Vec<(usize, usize)> = Vec::new();
+		for it in inner {
+			match ranges.last_mut() {
+				Some(last) if it.sta() < last.1 => last.1 = last.1.max(it.end()),
+				_ => ranges.push((it.sta(), it.end())),
+			}
+		}
+		ranges
+	}
+
 	fn queue_reindex(&'static self) {
 		if self
 			.pending_reindex
@@ -267,8 +362,11 @@ impl BindingMap {
 impl Default for BindingMap {
 	fn default() -> Self {
 		Self {
+			binds: Default::default(),
+			spans: Default::default(),
 			nodes: Default::default(),
 			new_nodes: Default::default(),
+			log: Default::default(),
 			pending: Default::default(),
 			complete: Default::default(),
 			pending_reindex: false.into(),
@@ -277,3 +375,91 @@ impl Default for BindingMap {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Eval that replaces its nodes with a fixed value.
+	#[derive(Debug)]
+	struct Meaning(&'static str, Precedence);
+
+	impl Eval for Meaning {
+		fn precedence(&self) -> Precedence {
+			self.1
+		}
+
+		fn execute(&self, nodes: &[Node]) -> Result<()> {
+			for it in nodes {
+				it.set_value(Value::from(self.0));
+				it.set_done(true);
+			}
+			Ok(())
+		}
+	}
+
+	fn meanings(node: Node, output: &mut Vec<&'static str>) {
+		if let Some(value) = node.get_value().cast::<&'static str>() {
+			output.push(value);
+		}
+		for it in node.children() {
+			meanings(it, output);
+		}
+	}
+
+	#[test]
+	fn innermost_span_wins() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let text = "w\nif true:\n\tw\nelse:\n\tw\n";
+		let src = sources.from_string("scopes", text);
+		let first = text.find("\tw").unwrap();
+		let first = Span::new(src, first, first + 3);
+		let second = text.rfind("\tw").unwrap();
+		let second = Span::new(src, second, second + 3);
+
+		// the inner bindings run after the outer one, but still shadow it
+		let word = WORDS.get("w");
+		word.set_span(src.span(), Meaning("outer", Precedence::First));
+		word.set_span(first, Meaning("first", Precedence::VarBinding));
+		word.set_span(second, Meaning("second", Precedence::OpAdd));
+
+		let mut session = Session::new();
+		let program = session.load(&[src]);
+		let _ = session.solve(program);
+
+		let mut output = Vec::new();
+		meanings(program, &mut output);
+		assert_eq!(output, ["outer", "first", "second"]);
+		Ok(())
+	}
+
+	#[test]
+	fn nested_inner_spans() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let text = "w\nif true:\n\tw\n\tif true:\n\t\tw\nw\n";
+		let src = sources.from_string("nested", text);
+		let block = text.find("\tw").unwrap();
+		let block = Span::new(src, block, text.rfind("\nw").unwrap());
+		let inner = text.find("\t\tw").unwrap();
+		let inner = Span::new(src, inner, inner + 3);
+
+		let word = WORDS.get("w");
+		word.set_span(src.span(), Meaning("outer", Precedence::First));
+		// the outer bind skips the block, and the block skips the span in it
+		word.set_span(inner, Meaning("inner", Precedence::OpAdd));
+		word.set_span(block, Meaning("block", Precedence::VarBinding));
+
+		let mut session = Session::new();
+		let program = session.load(&[src]);
+		let _ = session.solve(program);
+
+		let mut output = Vec::new();
+		meanings(program, &mut output);
+		assert_eq!(output, ["outer", "block", "inner", "outer"]);
+		Ok(())
+	}
+}
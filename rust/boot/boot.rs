@@ -18,7 +18,7 @@ use std::{
 	borrow::Cow,
 	cell::UnsafeCell,
 	cmp::Ordering,
-	collections::{HashMap, VecDeque},
+	collections::{BTreeMap, HashMap, HashSet, VecDeque},
 	fmt::{Debug, Display, Formatter},
 	hash::Hash,
 	io::Write,
@@ -38,15 +38,19 @@ mod cmd;
 mod code;
 mod core;
 mod cursor;
+mod diagnostics;
 mod eval;
+mod export;
 mod format;
 mod heap;
 mod iter;
 mod lexer;
 mod list;
 mod node;
+mod pattern;
 mod queue;
 mod result;
+mod session;
 mod source;
 mod span;
 mod symbol;
@@ -54,6 +58,7 @@ mod table;
 mod temp;
 mod term;
 mod token;
+mod trace;
 mod unicode;
 mod value;
 
@@ -64,14 +69,17 @@ pub use cmd::*;
 pub use code::*;
 pub use core::*;
 pub use cursor::*;
+pub use diagnostics::*;
 pub use eval::*;
 pub use format::*;
 pub use iter::*;
 pub use lexer::*;
 pub use list::*;
 pub use node::*;
+pub use pattern::*;
 pub use queue::*;
 pub use result::*;
+pub use session::*;
 pub use source::*;
 pub use span::*;
 pub use symbol::*;
@@ -79,6 +87,7 @@ pub use table::*;
 pub use temp::*;
 pub use term::*;
 pub use token::*;
+pub use trace::*;
 pub use unicode::*;
 pub use value::*;
 
@@ -89,7 +98,12 @@ pub enum Message<'a, 'b> {
 	Output(Node, &'a mut Writer<'b>),
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+/// Order in which evals are executed by the binding queue.
+///
+/// Besides the built-in levels, [`Precedence::Operator`] inserts a level
+/// for operators declared in the source between the built-in operators
+/// (see [`Precedence::OPERATOR_LEVELS`]).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum Precedence {
 	First,
 	Source,
@@ -97,37 +111,134 @@ pub enum Precedence {
 	Indent,
 	ExpandRaw,
 	Comment,
+	OperatorDecl,
+	Import,
+	Interpolation,
+	Brackets,
+	Member,
 	LetDecl,
 	LetExpr,
+	Assign,
 	BlockParse,
+	Print,
 	VarBinding,
+	SessionBinding,
+	Builtin,
+	Index,
+	OpOr,
+	OpAnd,
+	OpNot,
+	OpCompare,
 	OpIn,
 	OpRange,
 	OpAdd,
 	OpMul,
-	Print,
+	OpUnary,
 	BlockEval,
 	Output,
 	Literal,
 	Last,
+	/// Operator declared in the source with a level from 0 (loosest) to 9.
+	Operator(u8),
 }
 
-#[derive(Default)]
-pub struct Options {
-	pub show_program: bool,
-	pub dump_code: bool,
-	pub compile: bool,
+impl Precedence {
+	/// Built-in operators for each declared operator level. A declared
+	/// operator binds tighter than the built-in ones at its level and
+	/// looser than the ones at the next.
+	pub const OPERATOR_LEVELS: [Precedence; 10] = [
+		Precedence::SessionBinding,
+		Precedence::SessionBinding,
+		Precedence::OpOr,
+		Precedence::OpAnd,
+		Precedence::OpCompare,
+		Precedence::OpRange,
+		Precedence::OpAdd,
+		Precedence::OpMul,
+		Precedence::OpMul,
+		Precedence::OpMul,
+	];
+
+	/// Position in the evaluation order. A declared operator is placed
+	/// right after the built-in level for it, ordered by its own level.
+	fn order(&self) -> (u8, u8) {
+		let index = match self {
+			Precedence::First => 0,
+			Precedence::Source => 1,
+			Precedence::LineSplit => 2,
+			Precedence::Indent => 3,
+			Precedence::ExpandRaw => 4,
+			Precedence::Comment => 5,
+			Precedence::OperatorDecl => 6,
+			Precedence::Import => 7,
+			Precedence::Interpolation => 8,
+			Precedence::Brackets => 9,
+			Precedence::Member => 10,
+			Precedence::LetDecl => 11,
+			Precedence::LetExpr => 12,
+			Precedence::Assign => 13,
+			Precedence::BlockParse => 14,
+			Precedence::Print => 15,
+			Precedence::VarBinding => 16,
+			Precedence::SessionBinding => 17,
+			Precedence::Builtin => 18,
+			Precedence::Index => 19,
+			Precedence::OpOr => 20,
+			Precedence::OpAnd => 21,
+			Precedence::OpNot => 22,
+			Precedence::OpCompare => 23,
+			Precedence::OpIn => 24,
+			Precedence::OpRange => 25,
+			Precedence::OpAdd => 26,
+			Precedence::OpMul => 27,
+			Precedence::OpUnary => 28,
+			Precedence::BlockEval => 29,
+			Precedence::Output => 30,
+			Precedence::Literal => 31,
+			Precedence::Last => 32,
+			&Precedence::Operator(level) => {
+				let after = Self::OPERATOR_LEVELS[level as usize];
+				return (after.order().0, level + 1);
+			}
+		};
+		(index, 0)
+	}
 }
 
-pub fn init_core() {
+impl Ord for Precedence {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.order().cmp(&other.order())
+	}
+}
+
+impl PartialOrd for Precedence {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+/// Lexer with the symbols for the core language.
+pub fn core_lexer() -> Lexer {
 	let lexer = Lexer::new();
+	lexer.add_symbols(["=", ",", ":", ".", "(", ")", "[", "]"]);
+	lexer.add_symbols(["+", "-", "*", "/", "%"]);
+	lexer.add_symbols(["==", "!=", "<", "<=", ">", ">="]);
+	lexer.add_symbols(["..", "..="]);
+	lexer
+}
+
+pub fn init_core() {
+	let lexer = core_lexer();
 	let symbols = Symbols::get();
 
+	let interpolation = ParseInterpolation(lexer.clone());
+
 	let sources = SOURCES.get();
 	sources.add_global_init(DefaultLexer(lexer));
 
 	let raw = RAW.get();
 	raw.add_eval(SplitLines);
+	raw.add_eval(ParseIndent);
 	raw.add_eval(ExpandRaw);
 
 	let comment = COMMENT.get();
@@ -136,7 +247,58 @@ pub fn init_core() {
 	let print = WORDS.get(symbols.PRINT);
 	print.add_eval(ParsePrint);
 
+	let decl = WORDS.get(symbols.LET);
+	decl.add_eval(ParseLet);
+
+	SYMBOLS.get(symbols.ASSIGN).add_eval(ParseAssign);
+
+	WORDS.get(symbols.IF).add_eval(ParseIf);
+	WORDS.get(symbols.ELIF).add_eval(ParseElse);
+	WORDS.get(symbols.ELSE).add_eval(ParseElse);
+	WORDS.get(symbols.WHILE).add_eval(ParseWhile);
+	WORDS.get(symbols.FOR).add_eval(ParseFor);
+	WORDS.get(symbols.BREAK).add_eval(ParseLoopControl);
+	WORDS.get(symbols.CONTINUE).add_eval(ParseLoopControl);
+
+	WORDS.get(symbols.OPERATOR).add_eval(ParseOperatorDecl);
+	for level in 0..Precedence::OPERATOR_LEVELS.len() {
+		OPERATORS.get().add_eval(ParseOperator(level as u8));
+	}
+
+	WORDS.get(symbols.IMPORT).add_eval(ParseImport);
+	WORDS.get(symbols.EXPORT).add_eval(ParseExport);
+
+	WORDS.get(symbols.FN).add_eval(ParseFn);
+	WORDS.get(symbols.RETURN).add_eval(ParseReturn);
+
+	WORDS.get(symbols.STRUCT).add_eval(ParseStruct);
+	SYMBOLS.get(symbols.DOT).add_eval(ParseMember);
+	MEMBERS.get().add_eval(ParseField);
+
+	SYMBOLS.get(symbols.RANGE).add_eval(ParseRange);
+	SYMBOLS.get(symbols.RANGE_INCLUSIVE).add_eval(ParseRange);
+
+	SYMBOLS.get(symbols.STA_PAREN).add_eval(ParseBrackets);
+	SYMBOLS.get(symbols.END_PAREN).add_eval(ParseBrackets);
+	SYMBOLS.get(symbols.STA_BRACKET).add_eval(ParseBrackets);
+	SYMBOLS.get(symbols.END_BRACKET).add_eval(ParseBrackets);
+	BRACKETS.get().add_eval(ParseIndex);
+
+	WORDS.get(symbols.LEN).add_eval(ParseBuiltin(Builtin::Len));
+	WORDS.get(symbols.APPEND).add_eval(ParseBuiltin(Builtin::Append));
+
+	for op in Op::BINARY {
+		let bindings = if op.is_word() { &WORDS } else { &SYMBOLS };
+		bindings.get(op.symbol()).add_eval(ParseBinaryOp(op.precedence()));
+	}
+
+	for op in Op::UNARY {
+		let bindings = if op.is_word() { &WORDS } else { &SYMBOLS };
+		bindings.get(op.symbol()).add_eval(ParseUnaryOp(op.precedence()));
+	}
+
 	let literal = LITERAL.get();
+	literal.add_eval(interpolation);
 	literal.add_eval(ParseLiteral);
 
 	let integer = INTEGER.get();
@@ -149,60 +311,46 @@ pub fn init_core() {
 	WORDS.get(symbols.FALSE).add_eval(ParseLiteral);
 }
 
-pub fn execute(input: &[Source], options: Options) -> Result<()> {
-	let program = Node::new_at(Program, Span::empty());
-	for it in input.iter().copied() {
-		let span = it.span();
-		let node = Node::new_at(it, span);
-		program.push_node(node);
-	}
-
-	let err = Queue::process();
-	program.set_done(true);
-
-	let err = err.and_then(|_| Node::check_pending());
-
-	if options.show_program {
-		let mut out = Writer::stdout();
-		write!(out, "\n========= PROGRAM =========\n\n")?;
-		program.write(&mut out)?;
-		write!(out, "\n\n===========================\n")?;
-	}
-
-	let output = err.and_then(|_| {
-		let ctx = CodeContext::new();
-		program.value().output_code(ctx, program)
-	})?;
-
-	if options.dump_code {
-		println!("\n{output:#?}\n");
-	}
+/// Output format for errors reported by [`report`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ErrorFormat {
+	/// Human readable errors with source snippets.
+	#[default]
+	Text,
+	/// One JSON object per line for each error (see [`Error::output_json`]).
+	Json,
+}
 
-	if options.compile {
-		let mut builder = clang::Builder::new();
-		let code = output.generate_c(&mut builder)?;
-
-		let mut runner = builder.build(code);
-		if options.dump_code {
-			println!("\n{}\n", runner.code);
-		}
-
-		let status = runner.run()?;
-		if !status.success() {
-			raise!("finished with status {status}");
-		}
-	} else {
-		let mut rt = Runtime::default();
-		let value = output.execute(&mut rt)?;
-
-		if !value.is::<()>() {
-			println!("\nanswer = {value}");
-		}
-	}
+/// Prints the error to stderr in the given format.
+///
+/// Text errors use colors if stderr is a terminal.
+pub fn report(err: &Error, format: ErrorFormat) {
+	let mut out = Writer::stderr();
+	let _ = match format {
+		ErrorFormat::Text => report_text(&mut out, err),
+		ErrorFormat::Json => err.output_json(&mut out),
+	};
+}
 
+fn report_text(out: &mut Writer, err: &Error) -> Result<()> {
+	use std::io::IsTerminal;
+	let color = std::io::stderr().is_terminal();
+	write!(out, "\n")?;
+	err.output(out, color)?;
+	write!(out, "\n\n")?;
 	Ok(())
 }
 
 pub fn error<T: std::fmt::Display>(msg: T) {
 	let _ = term::error(std::io::stderr(), msg);
 }
+
+/// Initializes the core language for tests, returning a lock since tests
+/// share the global binding queue and list of pending nodes.
+#[cfg(test)]
+fn init_test() -> std::sync::MutexGuard<'static, ()> {
+	static INIT: Once = Once::new();
+	static LOCK: Mutex<()> = Mutex::new(());
+	INIT.call_once(init_core);
+	LOCK.lock().unwrap_or_else(|err| err.into_inner())
+}
@@ -6,7 +6,7 @@ use std::{
 	process::{Command, ExitStatus, Output, Stdio},
 };
 
-#[derive(Default, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub enum Kind {
 	#[default]
 	Void,
@@ -14,29 +14,108 @@ pub enum Kind {
 	I64,
 	Bool,
 	Float,
+	/// Heap allocated `bit_list` with items of the given kind.
+	List(&'static Kind),
+	/// Pointer to a heap allocated C `struct`. Like lists, struct values are
+	/// shared by reference.
+	Struct(Struct),
+	/// Item kind for an empty list literal, resolved by the [`Builder`] as
+	/// in [`Type::Infer`].
+	Infer(usize),
 }
 
 impl Kind {
+	pub fn list(item: Kind) -> Self {
+		Kind::List(Arena::get().store(item))
+	}
+
+	/// Returns the kind for a declared field type.
+	pub fn from_type(typ: Type, span: Span) -> Result<Self> {
+		let kind = match typ {
+			Type::Bool => Kind::Bool,
+			Type::Int => Kind::I64,
+			Type::Float => Kind::Float,
+			Type::Str => Kind::Str,
+			Type::List(item) => Kind::list(Kind::from_type(*item, span)?),
+			Type::Struct(typ) => Kind::Struct(typ),
+			_ => raise!(@span => "type {typ} is not supported in the C output"),
+		};
+		Ok(kind)
+	}
+
 	pub fn decl(&self, out: &mut String) {
 		match self {
-			Kind::Void => out.push_str("void"),
+			Kind::Void | Kind::Infer(..) => out.push_str("void"),
 			Kind::Str => out.push_str("const char*"),
 			Kind::I64 => out.push_str("int64_t"),
 			Kind::Bool => out.push_str("bool"),
 			Kind::Float => out.push_str("double"),
+			Kind::List(..) => out.push_str("bit_list*"),
+			Kind::Struct(typ) => {
+				let _ = write!(out, "struct {}*", struct_name(*typ));
+			}
 		}
 	}
 
 	pub fn fmt(&self) -> Option<&'static str> {
 		let out = match self {
-			Kind::Void => return None,
+			Kind::Void | Kind::Infer(..) => return None,
 			Kind::Str => "%s",
 			Kind::I64 => "%\" PRId64 \"",
 			Kind::Bool => "%s",
 			Kind::Float => "%g",
+			Kind::List(..) => "%s",
+			Kind::Struct(..) => "%s",
 		};
 		Some(out)
 	}
+
+	pub fn name(&self) -> Cow<'static, str> {
+		let name = match self {
+			Kind::Void | Kind::Infer(..) => "unit",
+			Kind::Str => "str",
+			Kind::I64 => "i64",
+			Kind::Bool => "bool",
+			Kind::Float => "f64",
+			Kind::List(item) => return format!("[{}]", item.name()).into(),
+			Kind::Struct(typ) => typ.name().as_str(),
+		};
+		name.into()
+	}
+
+	/// Is the kind free of unresolved type variables?
+	fn is_resolved(self) -> bool {
+		match self {
+			Kind::Infer(..) => false,
+			Kind::List(item) => item.is_resolved(),
+			_ => true,
+		}
+	}
+
+	/// Does the kind contain the given type variable?
+	fn mentions(self, id: usize) -> bool {
+		match self {
+			Kind::Infer(var) => var == id,
+			Kind::List(item) => item.mentions(id),
+			_ => false,
+		}
+	}
+
+	/// Returns the C expression used to format a value with [`Kind::fmt`].
+	pub fn fmt_arg(&self, builder: &mut Builder, value: &str) -> String {
+		match self {
+			Kind::Bool => format!("({value}) ? \"true\" : \"false\""),
+			Kind::List(item) => {
+				let name = define_list_str(builder, **item);
+				format!("{name}({value})")
+			}
+			Kind::Struct(typ) => {
+				let name = define_struct_str(builder, *typ);
+				format!("{name}({value})")
+			}
+			_ => value.to_string(),
+		}
+	}
 }
 
 #[derive(Default)]
@@ -51,6 +130,16 @@ impl Func {
 		Self::default()
 	}
 
+	/// Returns the body followed by the expression as a statement.
+	pub fn into_statements(self) -> String {
+		let mut body = self.body;
+		if !self.expr.is_empty() {
+			body.push_str(&self.expr);
+			body.push_str(";\n");
+		}
+		body
+	}
+
 	pub fn i64(value: i64) -> Self {
 		let body = String::new();
 		let kind = Kind::I64;
@@ -84,7 +173,8 @@ impl Func {
 	}
 
 	pub fn float(value: f64) -> Self {
-		let expr = value.to_string();
+		// use the debug format to make sure the literal is not an integer
+		let expr = format!("{value:?}");
 		Self {
 			expr,
 			kind: Kind::Float,
@@ -158,7 +248,24 @@ fn output_char(chr: char, out: &mut String) {
 pub struct Builder {
 	include_system: Vec<&'static str>,
 	include_header: Vec<&'static str>,
+	helpers: Vec<(&'static str, String)>,
 	vars: u64,
+	names: HashMap<Var, (String, Kind)>,
+	outer_names: Vec<HashMap<Var, (String, Kind)>>,
+	functions: Vec<FuncInstance>,
+	current: Vec<usize>,
+	deferred: Option<Error>,
+	infer: Vec<Kind>,
+	infer_str: Vec<(&'static str, Kind)>,
+}
+
+/// C function generated for a function with a given set of argument kinds.
+struct FuncInstance {
+	func: Function,
+	args: Vec<Kind>,
+	name: String,
+	ret: Option<Kind>,
+	code: Option<(String, String)>,
 }
 
 impl Builder {
@@ -182,12 +289,215 @@ impl Builder {
 		}
 	}
 
+	/// Adds a helper definition to the program output, unless a helper with
+	/// the same name has already been defined.
+	pub fn define<T: AsRef<str>>(&mut self, name: &'static str, code: T) {
+		if !self.helpers.iter().any(|x| x.0 == name) {
+			self.helpers.push((name, text(code)));
+		}
+	}
+
 	pub fn var(&mut self) -> u64 {
 		self.vars += 1;
 		self.vars
 	}
 
-	pub fn build(&self, main: Func) -> Runner {
+	/// Stores the expression in a temporary variable, returning its name.
+	pub fn temp(&mut self, body: &mut String, kind: Kind, expr: &str) -> String {
+		let var = self.var();
+		kind.decl(body);
+		let _ = writeln!(body, " _${var}_ = {expr};");
+		format!("_${var}_")
+	}
+
+	pub fn declare_var(&mut self, var: Var, kind: Kind) -> String {
+		let name = Modules::mangle(var.name().as_str(), var.span().source());
+		let name = format!("{name}${}", var.id());
+		self.names.insert(var, (name.clone(), kind));
+		name
+	}
+
+	/// Returns a new unresolved type variable.
+	fn new_infer(&mut self) -> Kind {
+		self.infer.push(Kind::Void);
+		Kind::Infer(self.infer.len() - 1)
+	}
+
+	/// Replaces the resolved type variables in the kind.
+	fn resolve(&self, kind: Kind) -> Kind {
+		match kind {
+			Kind::Infer(id) if self.infer[id] != Kind::Void => self.resolve(self.infer[id]),
+			Kind::List(item) => {
+				let resolved = self.resolve(*item);
+				if resolved == *item {
+					kind
+				} else {
+					Kind::list(resolved)
+				}
+			}
+			kind => kind,
+		}
+	}
+
+	/// Checks that a value of the `actual` kind can be stored where
+	/// `expected` is, binding any unresolved type variable on either side.
+	fn unify(&mut self, expected: Kind, actual: Kind) -> bool {
+		let (expected, actual) = (self.resolve(expected), self.resolve(actual));
+		match (expected, actual) {
+			(Kind::Void, _) | (_, Kind::Void) => false,
+			(Kind::Infer(id), kind) | (kind, Kind::Infer(id)) if expected != actual => {
+				if kind.mentions(id) {
+					return false;
+				}
+				self.infer[id] = kind;
+				true
+			}
+			(Kind::List(a), Kind::List(b)) => self.unify(*a, *b),
+			(expected, actual) => expected == actual,
+		}
+	}
+
+	pub fn get_var(&self, var: Var) -> Option<(&str, Kind)> {
+		self.names.get(&var).map(|(name, kind)| (name.as_str(), *kind))
+	}
+
+	/// Returns the C function name and return kind for calling the function
+	/// with the given argument kinds, generating the function if needed.
+	pub fn call_function(&mut self, func: Function, args: Vec<Kind>, span: Span) -> Result<(String, Kind)> {
+		let found = self.functions.iter().position(|x| x.func == func && x.args == args);
+		if let Some(index) = found {
+			let it = &self.functions[index];
+			return match (it.ret, &it.code) {
+				(Some(ret), _) => Ok((it.name.clone(), self.resolve(ret))),
+				(None, Some(..)) => Ok((it.name.clone(), Kind::Void)),
+				(None, None) => {
+					let err = err!(@span => "cannot infer the return type for the recursive call to {func}");
+					self.deferred.get_or_insert(err.clone());
+					Err(err)
+				}
+			};
+		}
+
+		// names from imported modules are prefixed by the module path
+		let count = self.functions.iter().filter(|x| x.func == func).count();
+		let name = Modules::mangle(func.name().as_str(), func.span().source());
+		let name = if count == 0 {
+			format!("{name}${}", func.id())
+		} else {
+			format!("{name}${}${count}", func.id())
+		};
+
+		let index = self.functions.len();
+		self.functions.push(FuncInstance {
+			func,
+			args: args.clone(),
+			name: name.clone(),
+			ret: None,
+			code: None,
+		});
+
+		// a recursive call generated before any return is deferred, as in
+		// the type pass, until the other returns set the return kind
+		let outer_deferred = self.deferred.take();
+		let body = loop {
+			let ret = self.functions[index].ret;
+			let body = self.generate_body(index, &args);
+			match self.deferred.take() {
+				None => break body,
+				Some(err) if self.functions[index].ret == ret => break Err(err),
+				Some(..) => {}
+			}
+		};
+		self.deferred = outer_deferred;
+
+		let (params, body) = match body {
+			Ok(body) => body,
+			Err(err) => {
+				self.functions.truncate(index);
+				return Err(err);
+			}
+		};
+		let body = body.into_statements();
+		let ret = self.resolve(self.functions[index].ret.unwrap_or_default());
+
+		let mut decl = String::from("static ");
+		ret.decl(&mut decl);
+		let _ = write!(decl, " {name}({params})");
+
+		// functions with a value must not reach the end of the body
+		let returns = body.lines().last().map(|x| x.starts_with("return")) == Some(true);
+
+		// nested calls are checked against the stack limit, as in the runtime
+		define_stack_check(self);
+		let at = Func::str(&func.span().to_string()).expr;
+		let label = Func::str(&func.to_string()).expr;
+		let mut code = format!("{decl} {{\n");
+		let _ = writeln!(code, "\tbit_stack_check({label}, {at});");
+		code.push_str(&indent_block(body));
+		if ret != Kind::Void && !returns {
+			define_panic(self);
+			let _ = writeln!(code, "\tbit_panic(\"function {func} did not return a value\", {at});");
+		}
+		code.push_str("}\n");
+
+		self.functions[index].code = Some((format!("{decl};\n"), code));
+		Ok((name, ret))
+	}
+
+	/// Generates the parameter list and body for a function instance.
+	fn generate_body(&mut self, index: usize, args: &[Kind]) -> Result<(String, Func)> {
+		let func = self.functions[index].func;
+
+		// functions can only access their own variables
+		self.outer_names.push(std::mem::take(&mut self.names));
+		let mut params = String::new();
+		for (n, (var, kind)) in func.params().iter().zip(args).enumerate() {
+			if n > 0 {
+				params.push_str(", ");
+			}
+			kind.decl(&mut params);
+			params.push(' ');
+			params.push_str(&self.declare_var(*var, *kind));
+		}
+
+		if params.is_empty() {
+			params.push_str("void");
+		}
+
+		self.current.push(index);
+		let body = func.body().and_then(|body| body.generate_c(self));
+		self.current.pop();
+		self.names = self.outer_names.pop().unwrap();
+		Ok((params, body?))
+	}
+
+	/// Sets the return kind for the function being generated.
+	fn set_return(&mut self, kind: Kind, span: Span) -> Result<()> {
+		let index = match self.current.last() {
+			Some(&index) => index,
+			None => raise!(@span => "`return` outside of a function"),
+		};
+
+		let (func, ret) = (self.functions[index].func, self.functions[index].ret);
+		match ret {
+			None => self.functions[index].ret = Some(kind),
+			Some(ret) if !self.unify(ret, kind) => {
+				let ret = self.resolve(ret);
+				raise!(@span => "function {func} returns {}, but it previously returned {}", kind.name(), ret.name());
+			}
+			Some(..) => {}
+		}
+		Ok(())
+	}
+
+	pub fn build(&mut self, main: Func) -> Runner {
+		// list formatting for item kinds that were resolved only after the
+		// list was printed
+		while let Some((name, item)) = self.infer_str.pop() {
+			let item = self.resolve(item);
+			define_list_str_as(self, name, item);
+		}
+
 		let mut program = Runner::new();
 
 		for it in self.include_system.iter() {
@@ -198,8 +508,30 @@ impl Builder {
 			program.append(format!("#include \"{it}\"\n"));
 		}
 
+		for (_, code) in self.helpers.iter() {
+			program.append("\n");
+			program.append(code);
+			program.append("\n");
+		}
+
+		if !self.functions.is_empty() {
+			program.append("\n");
+		}
+
+		for (proto, _) in self.functions.iter().filter_map(|x| x.code.as_ref()) {
+			program.append(proto);
+		}
+
+		for (_, code) in self.functions.iter().filter_map(|x| x.code.as_ref()) {
+			program.append("\n");
+			program.append(code);
+		}
+
 		program.append("\n");
 		program.append("int main(int argc, char *argv[]) {\n\t");
+		if self.helpers.iter().any(|x| x.0 == "bit_stack_check") {
+			program.append("char bit_stack;\n\tbit_stack_init(&bit_stack);\n\t");
+		}
 
 		program.append(indent_with(main.body, "", "\t"));
 		if main.expr.len() > 0 {
@@ -223,11 +555,19 @@ impl Code {
 				let mut body = String::new();
 				let mut expr = String::new();
 				let mut kind = Kind::Void;
-				for it in code.iter() {
-					let func = it.generate_c(builder)?;
-					body.push_str(&func.body);
-					kind = func.kind;
-					expr = func.expr;
+				for (n, it) in code.iter().enumerate() {
+					let func = match it.generate_c(builder) {
+						// skipped until the deferred call has a return kind
+						Err(..) if builder.deferred.is_some() => continue,
+						func => func?,
+					};
+					if n == code.len() - 1 {
+						body.push_str(&func.body);
+						kind = func.kind;
+						expr = func.expr;
+					} else {
+						body.push_str(&func.into_statements());
+					}
 				}
 				Func { expr, body, kind }
 			}
@@ -240,7 +580,434 @@ impl Code {
 				Func::i64(v)
 			}
 			Expr::Float(v) => Func::float(v),
-			Expr::Str(v) => Func::str(v),
+			Expr::Str(v) => {
+				// C strings end at the first NUL, which would silently cut the
+				// text short in the output
+				if v.contains('\0') {
+					raise!(@self.span => "strings containing `\\0` are not supported in the C output");
+				}
+				Func::str(v)
+			}
+			Expr::Let(var, code) => {
+				let func = code.generate_c(builder)?;
+				if func.kind == Kind::Void {
+					raise!(@code => "cannot initialize variable {var} with an expression without a value");
+				}
+
+				let name = builder.declare_var(var, func.kind);
+				let mut body = func.body;
+				func.kind.decl(&mut body);
+				let _ = writeln!(body, " {name} = {};", func.expr);
+				Func {
+					body,
+					expr: String::new(),
+					kind: Kind::Void,
+				}
+			}
+			Expr::Var(var) => {
+				let (name, kind) = match builder.get_var(var) {
+					Some(var) => var,
+					None => raise!(@self.span => "variable {var} is not declared in the output"),
+				};
+				Func {
+					body: String::new(),
+					expr: name.to_string(),
+					kind,
+				}
+			}
+			Expr::Set(var, code) => {
+				let (name, kind) = match builder.get_var(var) {
+					Some((name, kind)) => (name.to_string(), kind),
+					None => raise!(@self.span => "variable {var} is not declared in the output"),
+				};
+
+				let func = code.generate_c(builder)?;
+				if !builder.unify(kind, func.kind) {
+					let kind = builder.resolve(kind);
+					raise!(@code => "cannot assign {} to variable {var} of type {}", func.kind.name(), kind.name());
+				}
+
+				let mut body = func.body;
+				let _ = writeln!(body, "{name} = {};", func.expr);
+				Func {
+					body,
+					expr: String::new(),
+					kind: Kind::Void,
+				}
+			}
+			Expr::If(cond, when_true, otherwise) => {
+				let cond = generate_cond(builder, cond)?;
+
+				// both branches are generated, so that a deferred recursive
+				// call in one gets its return kind from the other
+				let when_true = when_true.generate_c(builder);
+				let otherwise = otherwise.generate_c(builder);
+				let (when_true, otherwise) = (when_true?, otherwise?);
+
+				let mut body = cond.body;
+				let _ = writeln!(body, "if ({}) {{", cond.expr);
+				body.push_str(&indent_block(when_true.into_statements()));
+				let otherwise = otherwise.into_statements();
+				if !otherwise.is_empty() {
+					body.push_str("} else {\n");
+					body.push_str(&indent_block(otherwise));
+				}
+				body.push_str("}\n");
+				Func {
+					body,
+					expr: String::new(),
+					kind: Kind::Void,
+				}
+			}
+			Expr::While(cond, code) => {
+				let cond = generate_cond(builder, cond)?;
+				let code = code.generate_c(builder)?.into_statements();
+
+				// conditions with a body must be evaluated inside the loop
+				let mut body = String::new();
+				let code = if !cond.body.is_empty() {
+					body.push_str("while (1) {\n");
+					format!("{}if (!({})) break;\n{code}", cond.body, cond.expr)
+				} else {
+					let _ = writeln!(body, "while ({}) {{", cond.expr);
+					code
+				};
+				body.push_str(&indent_block(code));
+				body.push_str("}\n");
+				Func {
+					body,
+					expr: String::new(),
+					kind: Kind::Void,
+				}
+			}
+			Expr::For(var, sta, end, inclusive, code) => {
+				let mut body = String::new();
+				let mut bound = |code: &Code| -> Result<String> {
+					let func = code.generate_c(builder)?;
+					if func.kind != Kind::I64 {
+						raise!(@code => "range bound must be an i64, but it is {}", func.kind.name());
+					}
+					body.push_str(&func.body);
+					Ok(builder.temp(&mut body, Kind::I64, &func.expr))
+				};
+
+				let sta = bound(sta)?;
+				let end = bound(end)?;
+				let name = builder.declare_var(var, Kind::I64);
+				let code = format!(
+					"int64_t {name} = {sta};\n{}",
+					code.generate_c(builder)?.into_statements()
+				);
+
+				// `continue` in a do-while jumps to the condition, which also
+				// avoids overflowing the counter at the end of the range
+				if inclusive {
+					let _ = writeln!(body, "if ({sta} <= {end}) {{");
+					body.push_str("\tdo {\n");
+					body.push_str(&indent_with(code, "\t\t", "\t\t"));
+					let _ = writeln!(body, "\t}} while ({sta} != {end} && (++{sta}, 1));");
+				} else {
+					let _ = writeln!(body, "for (; {sta} < {end}; {sta}++) {{");
+					body.push_str(&indent_block(code));
+				}
+				body.push_str("}\n");
+				Func {
+					body,
+					expr: String::new(),
+					kind: Kind::Void,
+				}
+			}
+			Expr::ForEach(var, list, code) => {
+				let list = list.generate_c(builder)?;
+				let item = match list.kind {
+					Kind::List(Kind::Infer(..)) => {
+						raise!(@self.span => "cannot infer the item type for the empty list")
+					}
+					Kind::List(item) => *item,
+					kind => raise!(@self.span => "for loop expects a range or a list, but it is {}", kind.name()),
+				};
+
+				// the loop runs over the items at the start, even if the body
+				// appends to the list
+				let mut body = list.body;
+				let items = builder.temp(&mut body, list.kind, &list.expr);
+				let len = builder.temp(&mut body, Kind::I64, &format!("{items}->len"));
+				let index = format!("_${}_", builder.var());
+				let name = builder.declare_var(var, item);
+
+				let mut next = String::new();
+				item.decl(&mut next);
+				let _ = writeln!(next, " {name} = {}[{index}];", list_items(item, &items));
+				next.push_str(&code.generate_c(builder)?.into_statements());
+
+				let _ = writeln!(body, "for (int64_t {index} = 0; {index} < {len}; {index}++) {{");
+				body.push_str(&indent_block(next));
+				body.push_str("}\n");
+				Func {
+					body,
+					expr: String::new(),
+					kind: Kind::Void,
+				}
+			}
+			Expr::List(items) => {
+				let mut list = Vec::new();
+				let item = builder.new_infer();
+				for it in items.iter() {
+					let func = it.generate_c(builder)?;
+					if !builder.unify(item, func.kind) {
+						let (kind, item) = (func.kind.name(), builder.resolve(item).name());
+						raise!(@it => "list item is {kind}, but the previous items are {item}");
+					}
+					list.push(func);
+				}
+
+				define_list(builder);
+				let item = builder.resolve(item);
+				let kind = Kind::list(item);
+				let mut body = String::new();
+				let size = list_size(item);
+				let out = builder.temp(&mut body, kind, &format!("bit_list_new({}, {size})", list.len()));
+				for (n, it) in list.into_iter().enumerate() {
+					body.push_str(&it.body);
+					let _ = writeln!(body, "{}[{n}] = {};", list_items(item, &out), it.expr);
+				}
+				Func { body, expr: out, kind }
+			}
+			Expr::Index(list, index_code) => {
+				let list = list.generate_c(builder)?;
+				let item = list_item(&list, self.span)?;
+				let index = index_code.generate_c(builder)?;
+				if index.kind != Kind::I64 {
+					raise!(@index_code => "list index must be an i64, but it is {}", index.kind.name());
+				}
+
+				let mut body = list.body;
+				let items = builder.temp(&mut body, list.kind, &list.expr);
+				body.push_str(&index.body);
+
+				// out of bounds errors point at the index, as in the runtime
+				let at = Func::str(&index_code.span.to_string()).expr;
+				let index = format!("bit_list_index({items}, {}, {at})", index.expr);
+				let expr = format!("{}[{index}]", list_items(item, &items));
+				Func { body, expr, kind: item }
+			}
+			Expr::Len(list) => {
+				let list = generate_list(builder, list)?;
+				Func {
+					body: list.body,
+					expr: format!("({})->len", list.expr),
+					kind: Kind::I64,
+				}
+			}
+			Expr::Append(list, item) => {
+				let list = generate_list(builder, list)?;
+				let item = item.generate_c(builder)?;
+				if item.kind == Kind::Void {
+					raise!(@self.span => "cannot append an expression without a value");
+				}
+
+				let list_item = match list.kind {
+					Kind::List(list_item) if builder.unify(*list_item, item.kind) => builder.resolve(item.kind),
+					kind => raise!(@self.span => "cannot append {} to a list of {}", item.kind.name(), kind.name()),
+				};
+
+				let mut body = list.body;
+				let out = if item.body.is_empty() {
+					list.expr
+				} else {
+					builder.temp(&mut body, list.kind, &list.expr)
+				};
+				body.push_str(&item.body);
+
+				let mut decl = String::new();
+				list_item.decl(&mut decl);
+				let size = list_size(list_item);
+				let _ = writeln!(body, "*({decl}*)bit_list_push({out}, {size}) = {};", item.expr);
+				Func {
+					body,
+					expr: String::new(),
+					kind: Kind::Void,
+				}
+			}
+			Expr::New(typ, args) => {
+				let name = define_struct(builder, typ)?;
+				let fields = typ.fields()?;
+				if args.len() != fields.len() {
+					raise!(@self.span => "struct {typ} has {} fields, but got {} values", fields.len(), args.len());
+				}
+
+				// each value is stored as soon as it is evaluated, which
+				// preserves the evaluation order
+				let kind = Kind::Struct(typ);
+				let mut body = String::new();
+				let out = builder.temp(&mut body, kind, &format!("malloc(sizeof(struct {name}))"));
+				for (field, arg) in fields.iter().zip(args.iter()) {
+					let field_kind = Kind::from_type(field.typ, field.span)?;
+					let value = arg.generate_c(builder)?;
+					if !builder.unify(field_kind, value.kind) {
+						let (name, kind) = (field.name.as_str(), value.kind.name());
+						raise!(@arg => "field `{name}` of {typ} is {}, but the value is {kind}", field_kind.name());
+					}
+					body.push_str(&value.body);
+					let _ = writeln!(body, "{out}->{} = {};", field_name(field.name), value.expr);
+				}
+				Func { body, expr: out, kind }
+			}
+			Expr::Field(value, name) => {
+				let (value, typ) = generate_struct(builder, value)?;
+				let (_, field) = typ.field(name, self.span)?;
+				Func {
+					body: value.body,
+					expr: format!("({})->{}", value.expr, field_name(name)),
+					kind: Kind::from_type(field.typ, field.span)?,
+				}
+			}
+			Expr::SetField(target, name, code) => {
+				let (target, typ) = generate_struct(builder, target)?;
+				let (_, field) = typ.field(name, self.span)?;
+				let kind = Kind::from_type(field.typ, field.span)?;
+				let value = code.generate_c(builder)?;
+				if !builder.unify(kind, value.kind) {
+					let (name, kind) = (name.as_str(), kind.name());
+					raise!(@code => "cannot assign {} to field `{name}` of type {kind}", value.kind.name());
+				}
+
+				let mut body = target.body;
+				let out = if value.body.is_empty() {
+					target.expr
+				} else {
+					builder.temp(&mut body, target.kind, &target.expr)
+				};
+				body.push_str(&value.body);
+				let _ = writeln!(body, "({out})->{} = {};", field_name(name), value.expr);
+				Func {
+					body,
+					expr: String::new(),
+					kind: Kind::Void,
+				}
+			}
+			Expr::Call(func, args) => {
+				let mut list = Vec::new();
+				for it in args.iter() {
+					let arg = it.generate_c(builder)?;
+					if arg.kind == Kind::Void {
+						raise!(@it => "cannot pass an expression without a value to {func}");
+					}
+					list.push(arg);
+				}
+
+				let kinds = list.iter().map(|x| x.kind).collect();
+				let (name, kind) = builder.call_function(func, kinds, self.span)?;
+
+				// arguments followed by a body are stored to preserve the
+				// evaluation order
+				let mut body = String::new();
+				let mut expr = format!("{name}(");
+				for n in 0..list.len() {
+					let arg = std::mem::take(&mut list[n]);
+					body.push_str(&arg.body);
+
+					let value = if list[n + 1..].iter().any(|x| !x.body.is_empty()) {
+						builder.temp(&mut body, arg.kind, &arg.expr)
+					} else {
+						arg.expr
+					};
+
+					if n > 0 {
+						expr.push_str(", ");
+					}
+					expr.push_str(&value);
+				}
+				expr.push(')');
+				Func { body, expr, kind }
+			}
+			Expr::Return(code) => {
+				let func = code.generate_c(builder)?;
+				builder.set_return(func.kind, code.span)?;
+
+				let mut body = func.body;
+				if func.kind == Kind::Void {
+					if !func.expr.is_empty() {
+						let _ = writeln!(body, "{};", func.expr);
+					}
+					body.push_str("return;\n");
+				} else {
+					let _ = writeln!(body, "return {};", func.expr);
+				}
+				Func {
+					body,
+					expr: String::new(),
+					kind: Kind::Void,
+				}
+			}
+			Expr::Break => Func {
+				body: "break;\n".to_string(),
+				expr: String::new(),
+				kind: Kind::Void,
+			},
+			Expr::Continue => Func {
+				body: "continue;\n".to_string(),
+				expr: String::new(),
+				kind: Kind::Void,
+			},
+			Expr::Concat(parts) => {
+				let mut list = Vec::new();
+				for it in parts.iter() {
+					let func = it.generate_c(builder)?;
+					if func.kind == Kind::Void {
+						raise!(@it => "cannot interpolate an expression without a value");
+					}
+					list.push(func);
+				}
+
+				// literal text goes directly into the format string
+				let mut body = String::new();
+				let mut fmt = String::new();
+				let mut vals = String::new();
+				for n in 0..list.len() {
+					if let Expr::Str(text) = parts[n].expr {
+						for chr in text.chars() {
+							if chr == '%' {
+								fmt.push_str("%%");
+							} else {
+								output_char(chr, &mut fmt);
+							}
+						}
+						continue;
+					}
+
+					let arg = std::mem::take(&mut list[n]);
+					body.push_str(&arg.body);
+
+					let value = if list[n + 1..].iter().any(|x| !x.body.is_empty()) {
+						builder.temp(&mut body, arg.kind, &arg.expr)
+					} else {
+						arg.expr
+					};
+
+					if arg.kind == Kind::I64 {
+						builder.include_system("inttypes.h");
+					}
+					fmt.push_str(arg.kind.fmt().unwrap());
+					let _ = write!(vals, ", {}", arg.kind.fmt_arg(builder, &value));
+				}
+
+				define_format(builder);
+				Func {
+					body,
+					expr: format!("bit_format(\"{fmt}\"{vals})"),
+					kind: Kind::Str,
+				}
+			}
+			Expr::Unary(op, arg) => {
+				let arg = arg.generate_c(builder)?;
+				generate_unary(builder, op, arg)?
+			}
+			Expr::Binary(op, lhs, rhs) => {
+				let lhs = lhs.generate_c(builder)?;
+				let rhs = rhs.generate_c(builder)?;
+				generate_binary(builder, op, lhs, rhs)?
+			}
 			Expr::Print(args) => {
 				builder.include_system("stdio.h");
 				let mut body = String::new();
@@ -250,6 +1017,11 @@ impl Code {
 				code.push_str("printf(\"");
 				for it in args.iter() {
 					let func = it.generate_c(builder)?;
+					if func.kind == Kind::Void {
+						body.push_str(&func.into_statements());
+						continue;
+					}
+
 					body.push_str(&func.body);
 
 					let var = if func.expr.len() > 0 {
@@ -269,11 +1041,8 @@ impl Code {
 						code.push_str(fmt);
 
 						if var > 0 {
-							let _ = if func.kind == Kind::Bool {
-								write!(vals, ", _${var}_ ? \"true\" : \"false\"")
-							} else {
-								write!(vals, ", _${var}_")
-							};
+							let value = func.kind.fmt_arg(builder, &format!("_${var}_"));
+							let _ = write!(vals, ", {value}");
 						}
 					}
 				}
@@ -289,10 +1058,473 @@ impl Code {
 				}
 			}
 		};
-		Ok(out)
+		let kind = builder.resolve(out.kind);
+		Ok(Func { kind, ..out })
+	}
+}
+
+/// Indents the statements for a nested C block.
+fn indent_block(code: String) -> String {
+	if code.is_empty() {
+		code
+	} else {
+		indent_with(code, "\t", "\t")
+	}
+}
+
+fn generate_cond(builder: &mut Builder, cond: &Code) -> Result<Func> {
+	let func = cond.generate_c(builder)?;
+	if func.kind != Kind::Bool {
+		raise!(@cond => "condition must be a bool, but it is {}", func.kind.name());
+	}
+	Ok(func)
+}
+
+fn generate_unary(builder: &mut Builder, op: Operator, arg: Func) -> Result<Func> {
+	let Func { body, expr, kind } = arg;
+	let expr = match (op.op, kind) {
+		(Op::Neg, Kind::I64) => {
+			let at = define_i64_op(builder, op);
+			format!("bit_neg_i64({expr}, {at})")
+		}
+		(Op::Neg, Kind::Float) => format!("(-{expr})"),
+		(Op::Not, Kind::Bool) => format!("(!{expr})"),
+		_ => raise!(@op => "invalid operand for `{}`: {}", op.op, kind.name()),
+	};
+	Ok(Func { body, expr, kind })
+}
+
+fn generate_binary(builder: &mut Builder, op: Operator, lhs: Func, rhs: Func) -> Result<Func> {
+	let mut body = lhs.body;
+	if lhs.kind != rhs.kind || lhs.kind == Kind::Void {
+		let (lhs, rhs) = (lhs.kind.name(), rhs.kind.name());
+		raise!(@op => "invalid operands for `{}`: {lhs} and {rhs}", op.op);
+	}
+
+	let kind = lhs.kind;
+	let sym = op.op.symbol();
+
+	// short-circuit operators only evaluate the right side conditionally
+	if let Op::And | Op::Or = op.op {
+		if kind != Kind::Bool {
+			raise!(@op => "invalid operands for `{sym}`: {} and {}", kind.name(), kind.name());
+		}
+
+		let out = builder.temp(&mut body, kind, &lhs.expr);
+		let cond = if op.op == Op::And { "" } else { "!" };
+		let _ = writeln!(body, "if ({cond}{out}) {{");
+		body.push_str(&indent_block(rhs.body));
+		let _ = write!(body, "\t{out} = {};\n}}\n", rhs.expr);
+		return Ok(Func { body, expr: out, kind });
+	}
+
+	let a = if !rhs.body.is_empty() {
+		builder.temp(&mut body, kind, &lhs.expr)
+	} else {
+		lhs.expr
+	};
+	body.push_str(&rhs.body);
+	let b = rhs.expr;
+
+	let (expr, kind) = match (op.op, kind) {
+		(Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Rem, Kind::I64) => {
+			let at = define_i64_op(builder, op);
+			let name = i64_op_name(op.op);
+			(format!("{name}({a}, {b}, {at})"), kind)
+		}
+		(Op::Rem, Kind::Float) => {
+			builder.include_system("math.h");
+			(format!("fmod({a}, {b})"), kind)
+		}
+		(Op::Add | Op::Sub | Op::Mul | Op::Div, Kind::Float) => (format!("({a} {sym} {b})"), kind),
+		(Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge, Kind::I64 | Kind::Float) => {
+			(format!("({a} {sym} {b})"), Kind::Bool)
+		}
+		(Op::Eq | Op::Ne, Kind::Bool) => (format!("({a} {sym} {b})"), Kind::Bool),
+		(Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge, Kind::Str) => {
+			builder.include_system("string.h");
+			(format!("(strcmp({a}, {b}) {sym} 0)"), Kind::Bool)
+		}
+		_ => raise!(@op => "invalid operands for `{sym}`: {} and {}", kind.name(), kind.name()),
+	};
+
+	if kind == Kind::Bool {
+		builder.include_system("stdbool.h");
+	}
+
+	Ok(Func { body, expr, kind })
+}
+
+fn i64_op_name(op: Op) -> &'static str {
+	match op {
+		Op::Add => "bit_add_i64",
+		Op::Sub => "bit_sub_i64",
+		Op::Mul => "bit_mul_i64",
+		Op::Div => "bit_div_i64",
+		Op::Rem => "bit_rem_i64",
+		Op::Neg => "bit_neg_i64",
+		_ => unreachable!(),
 	}
 }
 
+/// Defines the checked helper function for the integer operator and returns
+/// the location argument used to report errors.
+fn define_i64_op(builder: &mut Builder, op: Operator) -> String {
+	define_panic(builder);
+	builder.include_system("inttypes.h");
+
+	let name = i64_op_name(op.op);
+	let sym = op.op.symbol();
+	let code = match op.op {
+		Op::Add | Op::Sub | Op::Mul => {
+			let builtin = match op.op {
+				Op::Add => "__builtin_add_overflow",
+				Op::Sub => "__builtin_sub_overflow",
+				_ => "__builtin_mul_overflow",
+			};
+			format!(
+				r#"
+					static int64_t {name}(int64_t a, int64_t b, const char *at) {{
+						int64_t out;
+						if ({builtin}(a, b, &out)) {{
+							bit_panic("integer overflow in `{sym}`", at);
+						}}
+						return out;
+					}}
+				"#
+			)
+		}
+		Op::Div | Op::Rem => format!(
+			r#"
+				static int64_t {name}(int64_t a, int64_t b, const char *at) {{
+					if (b == 0) {{
+						bit_panic("division by zero", at);
+					}}
+					if (a == INT64_MIN && b == -1) {{
+						bit_panic("integer overflow in `{sym}`", at);
+					}}
+					return a {sym} b;
+				}}
+			"#
+		),
+		_ => format!(
+			r#"
+				static int64_t {name}(int64_t a, const char *at) {{
+					if (a == INT64_MIN) {{
+						bit_panic("integer overflow in `{sym}`", at);
+					}}
+					return -a;
+				}}
+			"#
+		),
+	};
+	builder.define(name, code);
+
+	Func::str(&op.span.to_string()).expr
+}
+
+/// Generates the code for a list expression, checking its kind.
+fn generate_list(builder: &mut Builder, code: &Code) -> Result<Func> {
+	let list = code.generate_c(builder)?;
+	match list.kind {
+		Kind::List(..) => Ok(list),
+		kind => raise!(@code => "expected a list, but it is {}", kind.name()),
+	}
+}
+
+/// Returns the item kind for a list, which must be known.
+fn list_item(list: &Func, span: Span) -> Result<Kind> {
+	match list.kind {
+		Kind::List(Kind::Infer(..)) => raise!(@span => "cannot infer the item type for the empty list"),
+		Kind::List(item) => Ok(*item),
+		kind => raise!(@span => "expected a list, but it is {}", kind.name()),
+	}
+}
+
+/// Returns the list items as a C array of the item kind.
+fn list_items(item: Kind, list: &str) -> String {
+	let mut decl = String::new();
+	item.decl(&mut decl);
+	format!("(({decl}*){list}->items)")
+}
+
+fn list_size(item: Kind) -> String {
+	if let Kind::Infer(..) = item {
+		return "0".to_string();
+	}
+
+	let mut decl = String::new();
+	item.decl(&mut decl);
+	format!("sizeof({decl})")
+}
+
+/// Defines `bit_list` and its helpers. Lists are shared by reference and
+/// grow as needed, with bounds checks for every index.
+fn define_list(builder: &mut Builder) {
+	define_panic(builder);
+	define_format(builder);
+	builder.include_system("inttypes.h");
+	builder.define(
+		"bit_list",
+		r#"
+			typedef struct {
+				int64_t len;
+				int64_t cap;
+				void *items;
+			} bit_list;
+
+			static bit_list *bit_list_new(int64_t len, size_t size) {
+				bit_list *list = malloc(sizeof(bit_list));
+				list->len = len;
+				list->cap = len;
+				list->items = malloc(len * size + 1);
+				return list;
+			}
+
+			static void *bit_list_push(bit_list *list, size_t size) {
+				if (list->len == list->cap) {
+					list->cap = list->cap < 4 ? 4 : list->cap * 2;
+					list->items = realloc(list->items, list->cap * size);
+				}
+				return (char *)list->items + size * list->len++;
+			}
+
+			static int64_t bit_list_index(bit_list *list, int64_t index, const char *at) {
+				if (index < 0 || index >= list->len) {
+					const char *fmt = "index %" PRId64 " is out of bounds for a list of length %" PRId64;
+					bit_panic(bit_format(fmt, index, list->len), at);
+				}
+				return index;
+			}
+		"#,
+	);
+}
+
+/// Defines the function converting a list with the item kind to a string,
+/// returning its name.
+fn define_list_str(builder: &mut Builder, item: Kind) -> &'static str {
+	fn suffix(kind: Kind) -> String {
+		match kind {
+			Kind::List(item) => format!("list_{}", suffix(*item)),
+			Kind::Struct(typ) => struct_name(typ),
+			Kind::Infer(id) => format!("infer{id}"),
+			kind => kind.name().into_owned(),
+		}
+	}
+
+	let name = Arena::get().str(format!("bit_list_str_{}", suffix(item)));
+	if item.is_resolved() {
+		define_list_str_as(builder, name, item);
+		return name;
+	}
+
+	// the item kind can still be resolved by code generated later, such as
+	// an append in a loop, so the function is only defined by the build
+	let proto = Arena::get().str(format!("{name}$proto"));
+	if !builder.helpers.iter().any(|x| x.0 == proto) {
+		builder.define(proto, format!("static const char *{name}(bit_list *list);"));
+		builder.infer_str.push((name, item));
+	}
+	name
+}
+
+/// Defines the function converting a list with the item kind to a string,
+/// using the given name.
+fn define_list_str_as(builder: &mut Builder, name: &'static str, item: Kind) {
+	let code = if let Some(fmt) = item.fmt() {
+		let mut decl = String::new();
+		item.decl(&mut decl);
+		let value = item.fmt_arg(builder, "it");
+		format!(
+			r#"
+				static const char *{name}(bit_list *list) {{
+					const char *out = "[";
+					for (int64_t i = 0; i < list->len; i++) {{
+						{decl} it = (({decl}*)list->items)[i];
+						out = bit_format("%s%s{fmt}", out, i > 0 ? ", " : "", {value});
+					}}
+					return bit_format("%s]", out);
+				}}
+			"#
+		)
+	} else {
+		format!(
+			r#"
+				static const char *{name}(bit_list *list) {{
+					(void)list;
+					return "[]";
+				}}
+			"#
+		)
+	};
+
+	define_format(builder);
+	builder.define(name, code);
+}
+
+/// Generates the code for a struct expression, returning its declaration.
+fn generate_struct(builder: &mut Builder, code: &Code) -> Result<(Func, Struct)> {
+	let value = code.generate_c(builder)?;
+	match value.kind {
+		Kind::Struct(typ) => Ok((value, typ)),
+		kind => raise!(@code => "expected a struct, but it is {}", kind.name()),
+	}
+}
+
+/// Returns the C tag for a struct. Tags are a separate namespace in C, so
+/// they cannot clash with variables and functions.
+fn struct_name(typ: Struct) -> String {
+	let name = Modules::mangle(typ.name().as_str(), typ.span().source());
+	format!("{name}${}", typ.id())
+}
+
+/// Returns the C member for a field, which is suffixed so that fields named
+/// after a C keyword are still valid.
+fn field_name(name: Symbol) -> String {
+	format!("{}$", name.as_str())
+}
+
+/// Defines the C `struct` for a struct declaration, returning its tag.
+///
+/// Struct fields are pointers, so they don't need the definition for other
+/// structs, only their tag.
+fn define_struct(builder: &mut Builder, typ: Struct) -> Result<String> {
+	let name = struct_name(typ);
+	let mut code = format!("struct {name} {{\n");
+	for field in typ.fields()? {
+		let kind = Kind::from_type(field.typ, field.span)?;
+		match kind {
+			Kind::Bool => builder.include_system("stdbool.h"),
+			Kind::I64 => builder.include_system("inttypes.h"),
+			Kind::List(..) => define_list(builder),
+			_ => {}
+		}
+		code.push('\t');
+		kind.decl(&mut code);
+		let _ = writeln!(code, " {};", field_name(field.name));
+	}
+	code.push_str("};\n");
+
+	builder.include_system("stdlib.h");
+	builder.define(Arena::get().str(format!("struct {name}")), code);
+	Ok(name)
+}
+
+/// Defines the function converting a struct to a string, returning its name.
+fn define_struct_str(builder: &mut Builder, typ: Struct) -> &'static str {
+	let tag = struct_name(typ);
+	let name = Arena::get().str(format!("bit_struct_str_{tag}"));
+
+	// the prototype allows the fields to refer back to the struct, such as
+	// in a list of the struct itself
+	let proto = Arena::get().str(format!("{name}$proto"));
+	if builder.helpers.iter().any(|x| x.0 == proto) {
+		return name;
+	}
+	builder.define(proto, format!("static const char *{name}(struct {tag} *value);"));
+
+	// struct values only exist once the declaration is compiled
+	let fields = typ.fields().unwrap_or_default();
+	let mut fmt = String::new();
+	let mut vals = String::new();
+	for (n, field) in fields.iter().enumerate() {
+		if n > 0 {
+			fmt.push_str(", ");
+		}
+		let kind = Kind::from_type(field.typ, field.span).unwrap_or_default();
+		let _ = write!(fmt, "{}={}", field.name.as_str(), kind.fmt().unwrap_or_default());
+
+		let value = format!("value->{}", field_name(field.name));
+		let _ = write!(vals, ", {}", kind.fmt_arg(builder, &value));
+	}
+
+	let label = typ.name().as_str();
+	define_format(builder);
+	builder.define(
+		name,
+		format!(
+			r#"
+				static const char *{name}(struct {tag} *value) {{
+					return bit_format("{label}({fmt})"{vals});
+				}}
+			"#
+		),
+	);
+	name
+}
+
+fn define_panic(builder: &mut Builder) {
+	builder.include_system("stdio.h");
+	builder.include_system("stdlib.h");
+	builder.define(
+		"bit_panic",
+		r#"
+			static void bit_panic(const char *msg, const char *at) {
+				fflush(stdout);
+				fprintf(stderr, "\nError: %s\n\n       @ %s\n\n", msg, at);
+				exit(1);
+			}
+		"#,
+	);
+}
+
+/// Defines `bit_stack_check`, which fails with an error instead of a crash
+/// once nested calls use three quarters of the stack limit, the same budget
+/// used by the runtime.
+fn define_stack_check(builder: &mut Builder) {
+	define_panic(builder);
+	define_format(builder);
+	builder.include_system("stdint.h");
+	builder.include_system("sys/resource.h");
+	builder.define(
+		"bit_stack_check",
+		r#"
+			static uintptr_t bit_stack_base;
+			static uintptr_t bit_stack_limit = UINTPTR_MAX;
+
+			static void bit_stack_init(char *base) {
+				struct rlimit limit;
+				bit_stack_base = (uintptr_t)base;
+				if (getrlimit(RLIMIT_STACK, &limit) == 0 && limit.rlim_cur != RLIM_INFINITY) {
+					bit_stack_limit = limit.rlim_cur / 4 * 3;
+				}
+			}
+
+			static void bit_stack_check(const char *func, const char *at) {
+				char here;
+				if (bit_stack_base - (uintptr_t)&here > bit_stack_limit) {
+					bit_panic(bit_format("stack overflow calling %s", func), at);
+				}
+			}
+		"#,
+	);
+}
+
+/// Defines `bit_format`, which returns a new string using `printf` style
+/// formatting.
+fn define_format(builder: &mut Builder) {
+	builder.include_system("stdarg.h");
+	builder.include_system("stdio.h");
+	builder.include_system("stdlib.h");
+	builder.define(
+		"bit_format",
+		r#"
+			static const char *bit_format(const char *fmt, ...) {
+				va_list args;
+				va_start(args, fmt);
+				int len = vsnprintf(NULL, 0, fmt, args);
+				va_end(args);
+
+				char *out = malloc(len + 1);
+				va_start(args, fmt);
+				vsnprintf(out, len + 1, fmt, args);
+				va_end(args);
+				return out;
+			}
+		"#,
+	);
+}
+
 #[derive(Default)]
 pub struct Runner {
 	pub code: String,
@@ -353,6 +1585,7 @@ impl Runner {
 			.arg(&src)
 			.arg("-o")
 			.arg("main.exe")
+			.arg("-lm")
 			.stderr(Stdio::piped())
 			.stdout(Stdio::piped())
 			.spawn()?;
@@ -430,6 +1663,19 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn nul_in_strings() {
+		let code = Code {
+			span: Span::empty(),
+			expr: Expr::Str(Arena::get().str("a\0b")),
+		};
+		let err = match code.generate_c(&mut Builder::new()) {
+			Ok(..) => panic!("expected an error for a NUL in a string"),
+			Err(err) => err.to_string(),
+		};
+		assert!(err.contains("not supported in the C output"), "{err}");
+	}
+
 	#[test]
 	#[cfg(off)]
 	fn compile_and_run() -> Result<()> {
@@ -453,6 +1699,27 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn stack_overflow() -> Result<()> {
+		let _lock = init_test();
+
+		let text = "fn deep(n):\n\tif n == 0:\n\t\treturn 0\n\treturn 1 + deep(n - 1)\nprint deep(1000000000)";
+		let src = SourceMap::new(".")?.from_string("deep", text);
+		let mut session = Session::new();
+		let program = session.load(&[src]);
+		session.solve(program)?;
+		let code = session.compile(program)?;
+
+		let mut builder = Builder::new();
+		let main = code.generate_c(&mut builder)?;
+		let out = builder.build(main).execute()?;
+		assert!(!out.status.success());
+
+		let stderr = String::from_utf8(out.stderr)?;
+		assert!(stderr.contains("stack overflow calling `deep`"), "{stderr}");
+		Ok(())
+	}
+
 	#[test]
 	fn hello_world() -> Result<()> {
 		let mut main = Runner::new();
@@ -0,0 +1,108 @@
+use super::*;
+
+use std::sync::OnceLock;
+
+/// Handle to a function declaration.
+///
+/// The function body is set once the declaration is compiled, which allows
+/// calls to be compiled before the function itself (e.g. recursion).
+#[derive(Copy, Clone)]
+pub struct Function {
+	data: &'static FunctionData,
+}
+
+struct FunctionData {
+	name: Symbol,
+	span: Span,
+	id: usize,
+	params: &'static [Var],
+	body: OnceLock<Code>,
+}
+
+impl Function {
+	pub fn new(name: Symbol, span: Span, params: Vec<Var>) -> Self {
+		static COUNTER: AtomicUsize = AtomicUsize::new(0);
+		let id = COUNTER.fetch_add(1, Order::Relaxed) + 1;
+		let store = Arena::get();
+		let params = store.slice(params);
+		let data = store.store(FunctionData {
+			name,
+			span,
+			id,
+			params,
+			body: OnceLock::new(),
+		});
+		Self { data }
+	}
+
+	pub fn name(&self) -> Symbol {
+		self.data.name
+	}
+
+	pub fn id(&self) -> usize {
+		self.data.id
+	}
+
+	pub fn params(&self) -> &'static [Var] {
+		self.data.params
+	}
+
+	pub fn body(&self) -> Result<&'static Code> {
+		match self.data.body.get() {
+			Some(body) => Ok(body),
+			None => raise!(@self.span() => "function {self} has not been compiled"),
+		}
+	}
+
+	pub fn set_body(&self, body: Code) -> Result<()> {
+		if self.data.body.set(body).is_err() {
+			raise!(@self.span() => "function {self} has already been compiled");
+		}
+		Ok(())
+	}
+
+	fn as_ptr(&self) -> *const FunctionData {
+		self.data
+	}
+}
+
+impl HasSpan for Function {
+	fn span(&self) -> Span {
+		self.data.span
+	}
+}
+
+impl Eq for Function {}
+
+impl PartialEq for Function {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_ptr() == other.as_ptr()
+	}
+}
+
+impl Hash for Function {
+	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+		self.as_ptr().hash(state);
+	}
+}
+
+impl Display for Function {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "`")?;
+		self.name().write_name(f)?;
+		write!(f, "`")?;
+		Ok(())
+	}
+}
+
+impl Debug for Function {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "Function(")?;
+		self.name().write_name(f)?;
+		let span = self.span();
+		if !span.is_empty() {
+			write!(f, " @ {span}")?;
+		}
+		write!(f, ")")
+	}
+}
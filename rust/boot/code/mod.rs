@@ -2,6 +2,18 @@ use super::*;
 
 pub mod clang;
 
+mod funcs;
+mod ops;
+mod structs;
+mod types;
+mod vars;
+
+pub use funcs::*;
+pub use ops::*;
+pub use structs::*;
+pub use types::*;
+pub use vars::*;
+
 #[derive(Copy, Clone)]
 pub struct CodeContext {
 	data: &'static CodeContextData,
@@ -48,6 +60,27 @@ pub enum Expr {
 	Int(i64),
 	Float(f64),
 	Str(&'static str),
+	Let(Var, &'static Code),
+	Var(Var),
+	Unary(Operator, &'static Code),
+	Binary(Operator, &'static Code, &'static Code),
+	Set(Var, &'static Code),
+	If(&'static Code, &'static Code, &'static Code),
+	While(&'static Code, &'static Code),
+	For(Var, &'static Code, &'static Code, bool, &'static Code),
+	ForEach(Var, &'static Code, &'static Code),
+	Break,
+	Continue,
+	Call(Function, &'static [Code]),
+	Return(&'static Code),
+	Concat(&'static [Code]),
+	List(&'static [Code]),
+	Index(&'static Code, &'static Code),
+	Len(&'static Code),
+	Append(&'static Code, &'static Code),
+	New(Struct, &'static [Code]),
+	Field(&'static Code, Symbol),
+	SetField(&'static Code, Symbol, &'static Code),
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -128,8 +161,110 @@ impl<T: Compilable> Compilable for &T {
 	}
 }
 
+/// Stack size assumed for the thread running the program when none was
+/// set with [`Runtime::set_stack_size`]. This is the default for spawned
+/// threads, including tests.
+const DEFAULT_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+static STACK_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_STACK_SIZE);
+
+pub struct Runtime {
+	frames: Vec<Frame>,
+	flow: Flow,
+	stack_base: usize,
+}
+
+/// Variables for the main program or a single function call.
 #[derive(Default)]
-pub struct Runtime;
+struct Frame {
+	vars: HashMap<Var, Value>,
+}
+
+/// Pending control flow for the innermost loop or function.
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+enum Flow {
+	#[default]
+	Normal,
+	Break,
+	Continue,
+	Return(Value),
+}
+
+impl Default for Runtime {
+	fn default() -> Self {
+		Self {
+			frames: vec![Frame::default()],
+			flow: Flow::default(),
+			stack_base: 0,
+		}
+	}
+}
+
+impl Runtime {
+	/// Sets the stack size of the thread running the program.
+	///
+	/// Nested function calls are executed recursively, so the number of
+	/// calls allowed depends on the available stack rather than on a fixed
+	/// count. A call uses a few KiB of stack, so a deeper recursion needs a
+	/// larger stack (see `bit --stack-size`). A quarter of the stack is kept
+	/// in reserve for the error path. The C output applies the same check
+	/// against the stack limit of the process.
+	pub fn set_stack_size(size: usize) {
+		STACK_SIZE.store(size, Order::Relaxed);
+	}
+
+	fn frame(&mut self) -> &mut Frame {
+		self.frames.last_mut().unwrap()
+	}
+
+	/// Consumes any pending `break` or `continue` for the current loop,
+	/// returning true if the loop must stop.
+	fn end_iteration(&mut self) -> bool {
+		match self.flow {
+			Flow::Normal => false,
+			Flow::Continue => {
+				self.flow = Flow::Normal;
+				false
+			}
+			Flow::Break => {
+				self.flow = Flow::Normal;
+				true
+			}
+			Flow::Return(..) => true,
+		}
+	}
+
+	fn call(&mut self, func: Function, args: &[Code], span: Span) -> Result<Value> {
+		let stack = &func as *const Function as usize;
+		if self.frames.len() == 1 {
+			self.stack_base = stack;
+		}
+
+		let used = self.stack_base.saturating_sub(stack);
+		let limit = STACK_SIZE.load(Order::Relaxed) / 4 * 3;
+		if used > limit {
+			let depth = self.frames.len();
+			raise!(@span => "stack overflow calling {func} ({depth} nested calls)");
+		}
+
+		let mut frame = Frame::default();
+		for (var, arg) in func.params().iter().zip(args) {
+			let value = arg.execute(self)?;
+			frame.vars.insert(*var, value);
+		}
+
+		let body = func.body()?;
+		self.frames.push(frame);
+		let result = body.execute(self);
+		self.frames.pop();
+		result?;
+
+		match std::mem::take(&mut self.flow) {
+			Flow::Return(value) => Ok(value),
+			_ => Ok(Value::new(())),
+		}
+	}
+}
 
 impl Code {
 	pub fn execute(&self, rt: &mut Runtime) -> Result<Value> {
@@ -139,6 +274,9 @@ impl Code {
 				let mut output = Value::new(());
 				for it in code {
 					output = it.execute(rt)?;
+					if rt.flow != Flow::Normal {
+						break;
+					}
 				}
 				output
 			}
@@ -163,9 +301,192 @@ impl Code {
 			Expr::Int(v) => Value::new(v),
 			Expr::Float(v) => Value::new(v),
 			Expr::Str(v) => Value::new(v),
+			Expr::Let(var, code) => {
+				let value = code.execute(rt)?;
+				rt.frame().vars.insert(var, value);
+				Value::new(())
+			}
+			Expr::Var(var) => match rt.frame().vars.get(&var) {
+				Some(value) => *value,
+				None => raise!(@self.span => "variable {var} used before being initialized"),
+			},
+			Expr::Set(var, code) => {
+				let value = code.execute(rt)?;
+				match rt.frame().vars.get_mut(&var) {
+					Some(slot) => *slot = value,
+					None => raise!(@self.span => "variable {var} assigned before being initialized"),
+				}
+				Value::new(())
+			}
+			Expr::If(cond, when_true, otherwise) => {
+				if cond.execute_cond(rt)? {
+					when_true.execute(rt)?;
+				} else {
+					otherwise.execute(rt)?;
+				}
+				Value::new(())
+			}
+			Expr::While(cond, body) => {
+				while cond.execute_cond(rt)? {
+					body.execute(rt)?;
+					if rt.end_iteration() {
+						break;
+					}
+				}
+				Value::new(())
+			}
+			Expr::For(var, sta, end, inclusive, body) => {
+				let sta = sta.execute_int(rt)?;
+				let end = end.execute_int(rt)?;
+				let mut next = Some(sta);
+				while let Some(n) = next.filter(|&n| n < end || (inclusive && n == end)) {
+					rt.frame().vars.insert(var, Value::new(n));
+					body.execute(rt)?;
+					if rt.end_iteration() {
+						break;
+					}
+					next = n.checked_add(1);
+				}
+				Value::new(())
+			}
+			Expr::ForEach(var, list, body) => {
+				// the loop runs over the items at the start, even if the body
+				// appends to the list
+				let list = list.execute_list(rt)?;
+				for item in list.items() {
+					rt.frame().vars.insert(var, *item);
+					body.execute(rt)?;
+					if rt.end_iteration() {
+						break;
+					}
+				}
+				Value::new(())
+			}
+			Expr::Break => {
+				rt.flow = Flow::Break;
+				Value::new(())
+			}
+			Expr::Continue => {
+				rt.flow = Flow::Continue;
+				Value::new(())
+			}
+			Expr::Call(func, args) => rt.call(func, args, self.span)?,
+			Expr::Return(code) => {
+				let value = code.execute(rt)?;
+				rt.flow = Flow::Return(value);
+				Value::new(())
+			}
+			Expr::Concat(parts) => {
+				let mut output = String::new();
+				for it in parts {
+					let value = it.execute(rt)?;
+					output.push_str(&value.to_string());
+				}
+				Value::new(Arena::get().str(output))
+			}
+			Expr::List(items) => {
+				let mut list = Vec::new();
+				for it in items {
+					list.push(it.execute(rt)?);
+				}
+				Value::new(List::new(list))
+			}
+			Expr::Index(list, index) => {
+				let items = list.execute_list(rt)?.items();
+				let value = index.execute(rt)?;
+				let at = match value.cast::<i64>() {
+					Some(&at) => at,
+					None => raise!(@index => "list index must be an i64, but it is {}", value_type_name(value)),
+				};
+				match usize::try_from(at).ok().and_then(|n| items.get(n)) {
+					Some(item) => *item,
+					None => raise!(@index => "index {at} is out of bounds for a list of length {}", items.len()),
+				}
+			}
+			Expr::Len(list) => {
+				let list = list.execute_list(rt)?;
+				Value::new(list.len() as i64)
+			}
+			Expr::Append(list, item) => {
+				let list = list.execute_list(rt)?;
+				let item = item.execute(rt)?;
+				list.push(item);
+				Value::new(())
+			}
+			Expr::New(typ, args) => {
+				let mut fields = Vec::new();
+				for it in args {
+					fields.push(it.execute(rt)?);
+				}
+				let fields = List::new(fields);
+				Value::new(StructValue { typ, fields })
+			}
+			Expr::Field(value, name) => {
+				let value = value.execute_struct(rt)?;
+				let (index, _) = value.typ.field(name, self.span)?;
+				value.get(index)
+			}
+			Expr::SetField(target, name, code) => {
+				let target = target.execute_struct(rt)?;
+				let (index, _) = target.typ.field(name, self.span)?;
+				let value = code.execute(rt)?;
+				target.set(index, value);
+				Value::new(())
+			}
+			Expr::Unary(op, arg) => {
+				let arg = arg.execute(rt)?;
+				op.eval_unary(arg)?
+			}
+			Expr::Binary(op, lhs, rhs) => {
+				let lhs = lhs.execute(rt)?;
+				let short_circuit = match op.op {
+					Op::And => lhs.cast::<bool>() == Some(&false),
+					Op::Or => lhs.cast::<bool>() == Some(&true),
+					_ => false,
+				};
+
+				if short_circuit {
+					lhs
+				} else {
+					let rhs = rhs.execute(rt)?;
+					op.eval_binary(lhs, rhs)?
+				}
+			}
 		};
 		Ok(value)
 	}
+
+	fn execute_int(&self, rt: &mut Runtime) -> Result<i64> {
+		let value = self.execute(rt)?;
+		match value.cast::<i64>() {
+			Some(&value) => Ok(value),
+			None => raise!(@self.span => "range bound must be an i64, but it is {}", value_type_name(value)),
+		}
+	}
+
+	fn execute_list(&self, rt: &mut Runtime) -> Result<List<Value>> {
+		let value = self.execute(rt)?;
+		match value.cast::<List<Value>>() {
+			Some(&list) => Ok(list),
+			None => raise!(@self.span => "expected a list, but it is {}", value_type_name(value)),
+		}
+	}
+
+	fn execute_struct(&self, rt: &mut Runtime) -> Result<StructValue> {
+		let value = self.execute(rt)?;
+		match value.cast::<StructValue>() {
+			Some(&value) => Ok(value),
+			None => raise!(@self.span => "expected a struct, but it is {}", value_type_name(value)),
+		}
+	}
+
+	fn execute_cond(&self, rt: &mut Runtime) -> Result<bool> {
+		let value = self.execute(rt)?;
+		match value.cast::<bool>() {
+			Some(&value) => Ok(value),
+			None => raise!(@self.span => "condition must be a bool, but it is {}", value_type_name(value)),
+		}
+	}
 }
 
 impl IsValue for () {}
@@ -201,3 +522,24 @@ impl IsValue for &'static str {
 }
 
 writable!(&'static str);
+
+/// Lists are shared, so changes to a list are visible to all its copies.
+impl IsValue for List<Value> {
+	fn as_writable(&self) -> Option<&dyn Writable> {
+		Some(self)
+	}
+}
+
+impl Writable for List<Value> {
+	fn write(&self, f: &mut Writer) -> Result<()> {
+		write!(f, "[")?;
+		for (n, it) in self.items().iter().enumerate() {
+			if n > 0 {
+				write!(f, ", ")?;
+			}
+			write!(f, "{it}")?;
+		}
+		write!(f, "]")?;
+		Ok(())
+	}
+}
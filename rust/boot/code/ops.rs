@@ -0,0 +1,262 @@
+use super::*;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Op {
+	Add,
+	Sub,
+	Mul,
+	Div,
+	Rem,
+	Neg,
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+	And,
+	Or,
+	Not,
+}
+
+impl Op {
+	pub const BINARY: [Op; 13] = [
+		Op::Add,
+		Op::Sub,
+		Op::Mul,
+		Op::Div,
+		Op::Rem,
+		Op::Eq,
+		Op::Ne,
+		Op::Lt,
+		Op::Le,
+		Op::Gt,
+		Op::Ge,
+		Op::And,
+		Op::Or,
+	];
+
+	pub const UNARY: [Op; 2] = [Op::Neg, Op::Not];
+
+	pub fn symbol(&self) -> &'static str {
+		match self {
+			Op::Add => "+",
+			Op::Sub => "-",
+			Op::Mul => "*",
+			Op::Div => "/",
+			Op::Rem => "%",
+			Op::Neg => "-",
+			Op::Eq => "==",
+			Op::Ne => "!=",
+			Op::Lt => "<",
+			Op::Le => "<=",
+			Op::Gt => ">",
+			Op::Ge => ">=",
+			Op::And => "and",
+			Op::Or => "or",
+			Op::Not => "not",
+		}
+	}
+
+	/// True for operators spelled as a word instead of a symbol.
+	pub fn is_word(&self) -> bool {
+		matches!(self, Op::And | Op::Or | Op::Not)
+	}
+
+	pub fn precedence(&self) -> Precedence {
+		match self {
+			Op::Or => Precedence::OpOr,
+			Op::And => Precedence::OpAnd,
+			Op::Not => Precedence::OpNot,
+			Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge => Precedence::OpCompare,
+			Op::Add | Op::Sub => Precedence::OpAdd,
+			Op::Mul | Op::Div | Op::Rem => Precedence::OpMul,
+			Op::Neg => Precedence::OpUnary,
+		}
+	}
+
+	pub fn binary(symbol: Symbol) -> Option<Op> {
+		let symbol = symbol.as_str();
+		Self::BINARY.into_iter().find(|op| op.symbol() == symbol)
+	}
+
+	pub fn unary(symbol: Symbol) -> Option<Op> {
+		let symbol = symbol.as_str();
+		Self::UNARY.into_iter().find(|op| op.symbol() == symbol)
+	}
+}
+
+impl Display for Op {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "{}", self.symbol())
+	}
+}
+
+/// Operator applied at a specific location in the source.
+#[derive(Copy, Clone, Debug)]
+pub struct Operator {
+	pub op: Op,
+	pub span: Span,
+}
+
+impl HasSpan for Operator {
+	fn span(&self) -> Span {
+		self.span
+	}
+}
+
+impl Operator {
+	pub fn eval_unary(&self, arg: Value) -> Result<Value> {
+		let op = self.op;
+		let value = match op {
+			Op::Neg => {
+				if let Some(&v) = arg.cast::<i64>() {
+					match v.checked_neg() {
+						Some(v) => Value::new(v),
+						None => raise!(@self.span => "integer overflow in `{op}`"),
+					}
+				} else if let Some(&v) = arg.cast::<f64>() {
+					Value::new(-v)
+				} else {
+					raise!(@self.span => "invalid operand for `{op}`: {}", value_type_name(arg))
+				}
+			}
+			Op::Not => {
+				if let Some(&v) = arg.cast::<bool>() {
+					Value::new(!v)
+				} else {
+					raise!(@self.span => "invalid operand for `{op}`: {}", value_type_name(arg))
+				}
+			}
+			_ => raise!(@self.span => "`{op}` is not an unary operator"),
+		};
+		Ok(value)
+	}
+
+	pub fn eval_binary(&self, lhs: Value, rhs: Value) -> Result<Value> {
+		if let (Some(&a), Some(&b)) = (lhs.cast::<i64>(), rhs.cast::<i64>()) {
+			self.eval_int(a, b)
+		} else if let (Some(&a), Some(&b)) = (lhs.cast::<f64>(), rhs.cast::<f64>()) {
+			self.eval_float(a, b)
+		} else if let (Some(&a), Some(&b)) = (lhs.cast::<&str>(), rhs.cast::<&str>()) {
+			self.eval_cmp(a, b)
+		} else if let (Some(&a), Some(&b)) = (lhs.cast::<bool>(), rhs.cast::<bool>()) {
+			match self.op {
+				Op::And => Ok(Value::new(a && b)),
+				Op::Or => Ok(Value::new(a || b)),
+				Op::Eq | Op::Ne => self.eval_cmp(a, b),
+				_ => self.invalid_operands(lhs, rhs),
+			}
+		} else {
+			self.invalid_operands(lhs, rhs)
+		}
+	}
+
+	pub fn check_unary(&self, arg: Type) -> Result<Type> {
+		let op = self.op;
+		match (op, arg) {
+			(Op::Neg, Type::Int | Type::Float) => Ok(arg),
+			(Op::Not, Type::Bool) => Ok(arg),
+			(Op::Neg | Op::Not, _) => raise!(@self.span => "invalid operand for `{op}`: {arg}"),
+			_ => raise!(@self.span => "`{op}` is not an unary operator"),
+		}
+	}
+
+	pub fn check_binary(&self, lhs: Type, rhs: Type) -> Result<Type> {
+		let op = self.op;
+		let typ = match op {
+			Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Rem if lhs == rhs && matches!(lhs, Type::Int | Type::Float) => {
+				lhs
+			}
+			Op::Lt | Op::Le | Op::Gt | Op::Ge if lhs == rhs && matches!(lhs, Type::Int | Type::Float | Type::Str) => {
+				Type::Bool
+			}
+			Op::Eq | Op::Ne if lhs == rhs && matches!(lhs, Type::Bool | Type::Int | Type::Float | Type::Str) => {
+				Type::Bool
+			}
+			Op::And | Op::Or if lhs == Type::Bool && rhs == Type::Bool => Type::Bool,
+			_ => raise!(@self.span => "invalid operands for `{op}`: {lhs} and {rhs}"),
+		};
+		Ok(typ)
+	}
+
+	fn eval_int(&self, a: i64, b: i64) -> Result<Value> {
+		let op = self.op;
+		let value = match op {
+			Op::Add => a.checked_add(b),
+			Op::Sub => a.checked_sub(b),
+			Op::Mul => a.checked_mul(b),
+			Op::Div | Op::Rem => {
+				if b == 0 {
+					raise!(@self.span => "division by zero");
+				}
+				if op == Op::Div {
+					a.checked_div(b)
+				} else {
+					a.checked_rem(b)
+				}
+			}
+			_ => return self.eval_cmp(a, b),
+		};
+
+		match value {
+			Some(value) => Ok(Value::new(value)),
+			None => raise!(@self.span => "integer overflow in `{op}`"),
+		}
+	}
+
+	fn eval_float(&self, a: f64, b: f64) -> Result<Value> {
+		let value = match self.op {
+			Op::Add => a + b,
+			Op::Sub => a - b,
+			Op::Mul => a * b,
+			Op::Div => a / b,
+			Op::Rem => a % b,
+			_ => return self.eval_cmp(a, b),
+		};
+		Ok(Value::new(value))
+	}
+
+	fn eval_cmp<T: PartialOrd + IsValue>(&self, a: T, b: T) -> Result<Value> {
+		let value = match self.op {
+			Op::Eq => a == b,
+			Op::Ne => a != b,
+			Op::Lt => a < b,
+			Op::Le => a <= b,
+			Op::Gt => a > b,
+			Op::Ge => a >= b,
+			_ => {
+				let (a, b) = (Value::new(a), Value::new(b));
+				return self.invalid_operands(a, b);
+			}
+		};
+		Ok(Value::new(value))
+	}
+
+	fn invalid_operands(&self, lhs: Value, rhs: Value) -> Result<Value> {
+		let op = self.op;
+		let lhs = value_type_name(lhs);
+		let rhs = value_type_name(rhs);
+		raise!(@self.span => "invalid operands for `{op}`: {lhs} and {rhs}")
+	}
+}
+
+pub fn value_type_name(value: Value) -> &'static str {
+	if value.is::<()>() {
+		"unit"
+	} else if value.is::<bool>() {
+		"bool"
+	} else if value.is::<i64>() {
+		"i64"
+	} else if value.is::<f64>() {
+		"f64"
+	} else if value.is::<&str>() {
+		"str"
+	} else if value.is::<List<Value>>() {
+		"list"
+	} else if let Some(value) = value.cast::<StructValue>() {
+		value.typ.name().as_str()
+	} else {
+		"unknown"
+	}
+}
@@ -0,0 +1,175 @@
+use super::*;
+
+use std::sync::OnceLock;
+
+/// Handle to a struct declaration.
+///
+/// Like a [`Function`] body, the fields are set once the declaration is
+/// compiled, since field types can refer to structs declared later.
+#[derive(Copy, Clone)]
+pub struct Struct {
+	data: &'static StructData,
+}
+
+struct StructData {
+	name: Symbol,
+	span: Span,
+	id: usize,
+	fields: OnceLock<&'static [Field]>,
+}
+
+/// Named field in a struct declaration.
+#[derive(Copy, Clone, Debug)]
+pub struct Field {
+	pub name: Symbol,
+	pub span: Span,
+	pub typ: Type,
+}
+
+impl Struct {
+	pub fn new(name: Symbol, span: Span) -> Self {
+		static COUNTER: AtomicUsize = AtomicUsize::new(0);
+		let id = COUNTER.fetch_add(1, Order::Relaxed) + 1;
+		let data = Arena::get().store(StructData {
+			name,
+			span,
+			id,
+			fields: OnceLock::new(),
+		});
+		Self { data }
+	}
+
+	pub fn name(&self) -> Symbol {
+		self.data.name
+	}
+
+	pub fn id(&self) -> usize {
+		self.data.id
+	}
+
+	pub fn fields(&self) -> Result<&'static [Field]> {
+		match self.data.fields.get() {
+			Some(fields) => Ok(fields),
+			None => raise!(@self.span() => "struct {self} has not been compiled"),
+		}
+	}
+
+	pub fn set_fields(&self, fields: Vec<Field>) -> Result<()> {
+		let fields = Arena::get().slice(fields);
+		if self.data.fields.set(fields).is_err() {
+			raise!(@self.span() => "struct {self} has already been compiled");
+		}
+		Ok(())
+	}
+
+	/// Returns the index and declaration for the field with the given name.
+	pub fn field(&self, name: Symbol, span: Span) -> Result<(usize, Field)> {
+		let fields = self.fields()?;
+		match fields.iter().position(|x| x.name == name) {
+			Some(index) => Ok((index, fields[index])),
+			None => {
+				let err = err!(@span => "struct {self} has no field `{}`", name.as_str());
+				Err(err.with_label(*self, "struct declared here"))
+			}
+		}
+	}
+
+	fn as_ptr(&self) -> *const StructData {
+		self.data
+	}
+}
+
+impl HasSpan for Struct {
+	fn span(&self) -> Span {
+		self.data.span
+	}
+}
+
+impl Eq for Struct {}
+
+impl PartialEq for Struct {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_ptr() == other.as_ptr()
+	}
+}
+
+impl Ord for Struct {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.id().cmp(&other.id())
+	}
+}
+
+impl PartialOrd for Struct {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Hash for Struct {
+	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+		self.as_ptr().hash(state);
+	}
+}
+
+impl Display for Struct {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "`")?;
+		self.name().write_name(f)?;
+		write!(f, "`")?;
+		Ok(())
+	}
+}
+
+impl Debug for Struct {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "Struct(")?;
+		self.name().write_name(f)?;
+		let span = self.span();
+		if !span.is_empty() {
+			write!(f, " @ {span}")?;
+		}
+		write!(f, ")")
+	}
+}
+
+/// Runtime value for a struct, with one value for each field.
+///
+/// Like lists, struct values are shared, so changing a field is visible to
+/// all its copies.
+#[derive(Copy, Clone, Debug)]
+pub struct StructValue {
+	pub typ: Struct,
+	pub fields: List<Value>,
+}
+
+impl StructValue {
+	pub fn get(&self, index: usize) -> Value {
+		self.fields.items()[index]
+	}
+
+	pub fn set(&self, index: usize, value: Value) {
+		let items = self.fields.items();
+		self.fields.replace_and_set(items, index..index + 1, [value]);
+	}
+}
+
+impl IsValue for StructValue {
+	fn as_writable(&self) -> Option<&dyn Writable> {
+		Some(self)
+	}
+}
+
+impl Writable for StructValue {
+	fn write(&self, f: &mut Writer) -> Result<()> {
+		write!(f, "{}(", self.typ.name().as_str())?;
+		let fields = self.typ.fields()?;
+		for (n, (field, value)) in fields.iter().zip(self.fields.items()).enumerate() {
+			if n > 0 {
+				write!(f, ", ")?;
+			}
+			write!(f, "{}={value}", field.name.as_str())?;
+		}
+		write!(f, ")")?;
+		Ok(())
+	}
+}
@@ -0,0 +1,664 @@
+use super::*;
+
+/// Static type for a [`Code`] expression.
+///
+/// This follows the same algebra as the runtime types in `bits`, reduced to
+/// the few builtin types supported by the bootstrapper. Declared structs are
+/// named types, identified by their declaration.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Type {
+	/// Lack of a type (e.g. a function that never returns a value).
+	#[default]
+	None,
+	/// Type with no possible values, used for expressions that never
+	/// complete, such as `return` and `break`.
+	Never,
+	/// Zero sized type for expressions without a value.
+	Unit,
+	Bool,
+	Int,
+	Float,
+	Str,
+	/// List with items of the given type.
+	List(&'static Type),
+	/// Struct with the fields from its declaration.
+	Struct(Struct),
+	/// Either of the two types.
+	Sum(&'static Type, &'static Type),
+	/// Type variable for the item type of an empty list literal, resolved by
+	/// the [`TypeChecker`] once the list is used with a known item type.
+	Infer(usize),
+}
+
+impl Type {
+	/// Is this the none type?
+	pub fn is_none(self) -> bool {
+		self == Type::None
+	}
+
+	/// A proper type is any type other than none.
+	pub fn is_proper(self) -> bool {
+		!self.is_none()
+	}
+
+	/// Is this an unresolved type variable?
+	pub fn is_infer(self) -> bool {
+		matches!(self, Type::Infer(..))
+	}
+
+	/// Is this a type for an actual value, as opposed to unit, never, or an
+	/// unresolved sum?
+	pub fn is_value(self) -> bool {
+		matches!(
+			self,
+			Type::Bool | Type::Int | Type::Float | Type::Str | Type::List(..) | Type::Struct(..)
+		)
+	}
+
+	/// Returns the builtin type with the given name, as used in a field
+	/// declaration.
+	pub fn builtin(name: &str) -> Option<Type> {
+		let typ = match name {
+			"bool" => Type::Bool,
+			"i64" => Type::Int,
+			"f64" => Type::Float,
+			"str" => Type::Str,
+			_ => return None,
+		};
+		Some(typ)
+	}
+
+	/// Returns a list type with the given item type.
+	pub fn list(item: Type) -> Type {
+		Type::List(Arena::get().store(item))
+	}
+
+	/// Return the sum of this type with the given type.
+	pub fn sum(self, other: Type) -> Type {
+		// none is ordered first, so it can only be on the right if both are
+		let (a, b) = if self < other { (self, other) } else { (other, self) };
+		if a.is_none() {
+			b
+		} else if a.contains(b) {
+			a
+		} else if b.contains(a) {
+			b
+		} else {
+			let store = Arena::get();
+			Type::Sum(store.store(a), store.store(b))
+		}
+	}
+
+	/// Is the current type a superset of the given type?
+	pub fn contains(self, other: Type) -> bool {
+		if self == other {
+			return true;
+		}
+
+		if let Type::Never | Type::None = other {
+			return true;
+		}
+
+		match (self, other) {
+			(Type::Sum(a, b), _) => a.contains(other) || b.contains(other),
+			(Type::List(a), Type::List(b)) => a.contains(*b),
+			_ => false,
+		}
+	}
+
+	pub fn name(&self) -> Cow<'static, str> {
+		let name = match self {
+			Type::None => "none",
+			Type::Never => "never",
+			Type::Unit => "unit",
+			Type::Bool => "bool",
+			Type::Int => "i64",
+			Type::Float => "f64",
+			Type::Str => "str",
+			Type::List(item) => return format!("[{}]", item.name()).into(),
+			Type::Struct(typ) => typ.name().as_str(),
+			Type::Sum(a, b) => return format!("{} | {}", a.name(), b.name()).into(),
+			Type::Infer(..) => "none",
+		};
+		name.into()
+	}
+
+	/// Does the type contain the given type variable?
+	fn mentions(self, id: usize) -> bool {
+		match self {
+			Type::Infer(var) => var == id,
+			Type::List(item) => item.mentions(id),
+			_ => false,
+		}
+	}
+}
+
+impl Display for Type {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+/// Typing pass over [`Code`], run before execution or C generation.
+///
+/// Functions are checked once for each distinct set of argument types,
+/// matching how they are instantiated by the C output.
+///
+/// A recursive call checked before any return has no known type yet. The
+/// statement containing it is deferred, and the function is checked again
+/// once the other returns have given it a type.
+///
+/// Each list literal gets a type variable for its items. Every use of the
+/// list unifies that variable, so that all aliases of an empty list share the
+/// item type given by its first use.
+#[derive(Default)]
+pub struct TypeChecker {
+	vars: HashMap<Var, Type>,
+	outer_vars: Vec<HashMap<Var, Type>>,
+	functions: Vec<FuncType>,
+	current: Vec<usize>,
+	locals: Vec<Var>,
+	used: HashSet<usize>,
+	deferred: Option<Error>,
+	infer: Vec<Type>,
+}
+
+/// Return type for a function checked with a given set of argument types.
+struct FuncType {
+	func: Function,
+	args: Vec<Type>,
+	ret: Type,
+	done: bool,
+}
+
+impl TypeChecker {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Warns about local variables that were checked but never read, and
+	/// resets the tracking for the next check.
+	///
+	/// Global variables are not included, since they can be used by later
+	/// programs in a session. Names starting with `_` are also ignored.
+	pub fn check_unused(&mut self) {
+		let used = std::mem::take(&mut self.used);
+		for var in std::mem::take(&mut self.locals) {
+			let name = var.name().as_str();
+			if !used.contains(&var.id()) && !name.starts_with('_') {
+				let warning = err!(@var => "unused variable `{name}`");
+				Diagnostics::push(warning.with_severity(Severity::Warning).with_help(format!(
+					"if this is intentional, prefix it with an underscore: `_{name}`"
+				)));
+			}
+		}
+	}
+
+	/// Declares a variable with the given type, tracking its usage if it is
+	/// a local variable.
+	fn declare(&mut self, var: Var, typ: Type, local: bool) {
+		if local && !self.locals.contains(&var) {
+			self.locals.push(var);
+		}
+		self.vars.insert(var, typ);
+	}
+
+	/// Returns a new unresolved type variable.
+	fn new_infer(&mut self) -> Type {
+		self.infer.push(Type::None);
+		Type::Infer(self.infer.len() - 1)
+	}
+
+	/// Replaces the resolved type variables in the type.
+	fn resolve(&self, typ: Type) -> Type {
+		match typ {
+			Type::Infer(id) if self.infer[id].is_proper() => self.resolve(self.infer[id]),
+			Type::List(item) => {
+				let resolved = self.resolve(*item);
+				if resolved == *item {
+					typ
+				} else {
+					Type::list(resolved)
+				}
+			}
+			typ => typ,
+		}
+	}
+
+	/// Checks that a value of the `actual` type can be used where `expected`
+	/// is, binding any unresolved type variable on either side.
+	fn unify(&mut self, expected: Type, actual: Type) -> bool {
+		let (expected, actual) = (self.resolve(expected), self.resolve(actual));
+		match (expected, actual) {
+			(Type::Infer(a), Type::Infer(b)) if a == b => true,
+			(Type::Infer(id), typ) | (typ, Type::Infer(id)) if typ.is_value() || typ.is_infer() => {
+				if typ.mentions(id) {
+					return false;
+				}
+				self.infer[id] = typ;
+				true
+			}
+			(Type::List(a), Type::List(b)) => self.unify(*a, *b),
+			(expected, actual) => expected.contains(actual),
+		}
+	}
+
+	fn call_function(&mut self, func: Function, args: Vec<Type>, span: Span) -> Result<Type> {
+		let found = self.functions.iter().position(|x| x.func == func && x.args == args);
+		if let Some(index) = found {
+			let it = &self.functions[index];
+			return match (self.resolve(it.ret), it.done) {
+				(Type::None, true) => Ok(Type::Unit),
+				(Type::None, false) => {
+					let err = err!(@span => "cannot infer the return type for the recursive call to {func}");
+					self.deferred.get_or_insert(err.clone());
+					Err(err)
+				}
+				(ret, _) => Ok(ret),
+			};
+		}
+
+		let index = self.functions.len();
+		self.functions.push(FuncType {
+			func,
+			args: args.clone(),
+			ret: Type::None,
+			done: false,
+		});
+
+		// check the body until no recursive call is deferred, failing if a
+		// check doesn't add to the return type
+		let outer_deferred = self.deferred.take();
+		let body = loop {
+			let ret = self.functions[index].ret;
+			let body = self.check_body(index, &args);
+			match self.deferred.take() {
+				None => break body,
+				Some(err) if self.functions[index].ret == ret => break Err(err),
+				Some(..) => {}
+			}
+		};
+		self.deferred = outer_deferred;
+
+		if let Err(err) = body {
+			self.functions.truncate(index);
+			return Err(err);
+		}
+
+		let it = &mut self.functions[index];
+		it.done = true;
+		if it.ret.is_proper() {
+			let ret = it.ret;
+			Ok(self.resolve(ret))
+		} else {
+			Ok(Type::Unit)
+		}
+	}
+
+	fn check_body(&mut self, index: usize, args: &[Type]) -> Result<Type> {
+		let func = self.functions[index].func;
+
+		// functions can only access their own variables
+		self.outer_vars.push(std::mem::take(&mut self.vars));
+		for (var, arg) in func.params().iter().zip(args) {
+			self.declare(*var, *arg, true);
+		}
+
+		self.current.push(index);
+		let body = func.body().and_then(|body| body.check_type(self));
+		self.current.pop();
+		self.vars = self.outer_vars.pop().unwrap();
+		body
+	}
+
+	/// Adds the type to the return type of the function being checked.
+	fn set_return(&mut self, typ: Type, span: Span) -> Result<()> {
+		let index = match self.current.last() {
+			Some(&index) => index,
+			None => raise!(@span => "`return` outside of a function"),
+		};
+
+		let (func, ret) = (self.functions[index].func, self.functions[index].ret);
+		let ret = match ret {
+			Type::None | Type::Never => ret.sum(typ),
+			_ if self.unify(ret, typ) => ret,
+			_ => raise!(@span => "function {func} returns {typ}, but it previously returned {}", self.resolve(ret)),
+		};
+		self.functions[index].ret = ret;
+		Ok(())
+	}
+}
+
+impl Code {
+	/// Returns the static type for the code, checking all nested expressions.
+	pub fn check_type(&self, checker: &mut TypeChecker) -> Result<Type> {
+		let typ = match self.expr {
+			Expr::None => Type::Unit,
+			Expr::Sequence(code) => {
+				let mut output = Type::Unit;
+				let mut unreachable = false;
+				for (n, it) in code.iter().enumerate() {
+					output = match it.check_type(checker) {
+						// skipped until the deferred call has a type
+						Err(..) if checker.deferred.is_some() => continue,
+						typ => typ?,
+					};
+					if output == Type::Never && !unreachable {
+						unreachable = check_unreachable(it, &code[n + 1..]);
+					}
+				}
+				output
+			}
+			Expr::Print(args) => {
+				for it in args {
+					it.check_type(checker)?;
+				}
+				Type::Unit
+			}
+			Expr::Bool(..) => Type::Bool,
+			Expr::Int(..) => Type::Int,
+			Expr::Float(..) => Type::Float,
+			Expr::Str(..) => Type::Str,
+			Expr::Let(var, code) => {
+				let typ = code.check_type(checker)?;
+				if !typ.is_value() {
+					raise!(@code => "cannot initialize variable {var} with an expression without a value");
+				}
+				let local = !checker.current.is_empty();
+				checker.declare(var, typ, local);
+				Type::Unit
+			}
+			Expr::Var(var) => {
+				checker.used.insert(var.id());
+				match checker.vars.get(&var) {
+					Some(&typ) => typ,
+					None => raise!(@self.span => "variable {var} used before being initialized"),
+				}
+			}
+			Expr::Set(var, code) => {
+				let typ = code.check_type(checker)?;
+				let var_type = match checker.vars.get(&var) {
+					Some(&typ) => typ,
+					None => raise!(@self.span => "variable {var} assigned before being initialized"),
+				};
+				if !typ.is_value() || !checker.unify(var_type, typ) {
+					let var_type = checker.resolve(var_type);
+					let err = err!(@code => "cannot assign {typ} to variable {var} of type {var_type}");
+					Err(err.with_label(var, format!("declared as {var_type}")))?;
+				}
+				Type::Unit
+			}
+			Expr::If(cond, when_true, otherwise) => {
+				cond.check_cond(checker)?;
+
+				// both branches are checked, so that a deferred recursive
+				// call in one gets its return type from the other
+				let when_true = when_true.check_type(checker);
+				otherwise.check_type(checker)?;
+				when_true?;
+				Type::Unit
+			}
+			Expr::While(cond, body) => {
+				cond.check_cond(checker)?;
+				body.check_type(checker)?;
+				Type::Unit
+			}
+			Expr::For(var, sta, end, _, body) => {
+				for it in [sta, end] {
+					let typ = it.check_type(checker)?;
+					if typ != Type::Int {
+						raise!(@it => "range bound must be an i64, but it is {typ}");
+					}
+				}
+				checker.declare(var, Type::Int, true);
+				body.check_type(checker)?;
+				Type::Unit
+			}
+			Expr::ForEach(var, list, body) => {
+				let item = match list.check_type(checker)? {
+					Type::List(item) if item.is_value() => *item,
+					Type::List(..) => raise!(@list => "cannot infer the item type for the empty list"),
+					typ => raise!(@list => "for loop expects a range or a list, but it is {typ}"),
+				};
+				checker.declare(var, item, true);
+				body.check_type(checker)?;
+				Type::Unit
+			}
+			Expr::Break | Expr::Continue => Type::Never,
+			Expr::Call(func, args) => {
+				let mut types = Vec::new();
+				for it in args.iter() {
+					let typ = it.check_type(checker)?;
+					if !typ.is_value() {
+						raise!(@it => "cannot pass an expression without a value to {func}");
+					}
+					types.push(typ);
+				}
+				checker.call_function(func, types, self.span)?
+			}
+			Expr::Return(code) => {
+				let typ = code.check_type(checker)?;
+				checker.set_return(typ, code.span)?;
+				Type::Never
+			}
+			Expr::Concat(parts) => {
+				for it in parts {
+					let typ = it.check_type(checker)?;
+					if !typ.is_value() {
+						raise!(@it => "cannot interpolate an expression without a value");
+					}
+				}
+				Type::Str
+			}
+			Expr::List(items) => {
+				let item_type = checker.new_infer();
+				for it in items {
+					let typ = it.check_type(checker)?;
+					if !typ.is_value() {
+						raise!(@it => "cannot use an expression without a value as a list item");
+					}
+
+					if !checker.unify(item_type, typ) {
+						let item_type = checker.resolve(item_type);
+						let err = err!(@it => "list item is {typ}, but the previous items are {item_type}");
+						Err(err.with_label(items[0], format!("list of {item_type}")))?;
+					}
+				}
+				Type::list(item_type)
+			}
+			Expr::Index(list, index) => {
+				let item = list.check_list(checker)?;
+				if !item.is_value() {
+					raise!(@list => "cannot infer the item type for the empty list");
+				}
+
+				let typ = index.check_type(checker)?;
+				if typ != Type::Int {
+					raise!(@index => "list index must be an i64, but it is {typ}");
+				}
+				item
+			}
+			Expr::Len(list) => {
+				list.check_list(checker)?;
+				Type::Int
+			}
+			Expr::Append(list, item) => {
+				let list_item = list.check_list(checker)?;
+				let typ = item.check_type(checker)?;
+				if !typ.is_value() {
+					raise!(@item => "cannot append an expression without a value");
+				}
+
+				if !checker.unify(list_item, typ) {
+					let err = err!(@item => "cannot append {typ} to a list of {list_item}");
+					Err(err.with_label(list, format!("list of {list_item}")))?;
+				}
+				Type::Unit
+			}
+			Expr::New(typ, args) => {
+				let fields = typ.fields()?;
+				if args.len() != fields.len() {
+					let s = if fields.len() == 1 { "" } else { "s" };
+					let err =
+						err!(@self.span => "struct {typ} has {} field{s}, but got {} values", fields.len(), args.len());
+					Err(err.with_label(typ, "struct declared here"))?;
+				}
+
+				for (field, arg) in fields.iter().zip(args.iter()) {
+					let arg_type = arg.check_type(checker)?;
+					if !arg_type.is_value() || !checker.unify(field.typ, arg_type) {
+						let name = field.name.as_str();
+						let err = err!(@arg => "field `{name}` of {typ} is {}, but the value is {arg_type}", field.typ);
+						Err(err.with_label(field.span, "field declared here"))?;
+					}
+				}
+				Type::Struct(typ)
+			}
+			Expr::Field(value, name) => {
+				let typ = value.check_struct(checker)?;
+				typ.field(name, self.span)?.1.typ
+			}
+			Expr::SetField(target, name, code) => {
+				let typ = target.check_struct(checker)?;
+				let (_, field) = typ.field(name, self.span)?;
+				let value = code.check_type(checker)?;
+				if !value.is_value() || !checker.unify(field.typ, value) {
+					let name = name.as_str();
+					let err = err!(@code => "cannot assign {value} to field `{name}` of type {}", field.typ);
+					Err(err.with_label(field.span, format!("declared as {}", field.typ)))?;
+				}
+				Type::Unit
+			}
+			Expr::Unary(op, arg) => {
+				let arg = arg.check_type(checker)?;
+				op.check_unary(arg)?
+			}
+			Expr::Binary(op, lhs, rhs) => {
+				let lhs_type = lhs.check_type(checker)?;
+				let rhs_type = rhs.check_type(checker)?;
+				let typ = op.check_binary(lhs_type, rhs_type);
+				typ.map_err(|err| err.with_label(lhs, lhs_type).with_label(rhs, rhs_type))?
+			}
+		};
+		Ok(checker.resolve(typ))
+	}
+
+	/// Checks that the code is a list, returning the item type.
+	fn check_list(&self, checker: &mut TypeChecker) -> Result<Type> {
+		match self.check_type(checker)? {
+			Type::List(item) => Ok(*item),
+			typ => raise!(@self.span => "expected a list, but it is {typ}"),
+		}
+	}
+
+	/// Checks that the code is a struct, returning its declaration.
+	fn check_struct(&self, checker: &mut TypeChecker) -> Result<Struct> {
+		match self.check_type(checker)? {
+			Type::Struct(typ) => Ok(typ),
+			typ => raise!(@self.span => "expected a struct, but it is {typ}"),
+		}
+	}
+
+	fn check_cond(&self, checker: &mut TypeChecker) -> Result<()> {
+		let typ = self.check_type(checker)?;
+		if typ != Type::Bool {
+			raise!(@self.span => "condition must be a bool, but it is {typ}");
+		}
+		Ok(())
+	}
+}
+
+/// Warns about code following an expression that never completes.
+///
+/// Returns true if there was any unreachable code.
+fn check_unreachable(code: &Code, rest: &[Code]) -> bool {
+	let rest = rest.iter().filter(|x| !matches!(x.expr, Expr::None));
+	let span = Span::for_range(rest);
+	if span.is_empty() {
+		return false;
+	}
+
+	let warning = err!(@span => "unreachable code");
+	let warning = warning.with_label(code, "any code following this is unreachable");
+	Diagnostics::push(warning.with_severity(Severity::Warning));
+	true
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sum_types() {
+		assert_eq!(Type::None.sum(Type::Int), Type::Int);
+		assert_eq!(Type::Int.sum(Type::None), Type::Int);
+		assert_eq!(Type::Int.sum(Type::Int), Type::Int);
+		assert_eq!(Type::Never.sum(Type::Str), Type::Str);
+
+		let sum = Type::Int.sum(Type::Str);
+		assert_eq!(sum, Type::Str.sum(Type::Int));
+		assert_eq!(sum.sum(Type::Int), sum);
+		assert!(sum.contains(Type::Int));
+		assert!(sum.contains(Type::Str));
+		assert!(!sum.contains(Type::Bool));
+		assert!(!Type::Int.contains(sum));
+
+		assert!(sum.is_proper());
+		assert!(!sum.is_value());
+		assert!(!Type::None.is_proper());
+	}
+
+	#[test]
+	fn list_types() {
+		let ints = Type::list(Type::Int);
+		let empty = Type::list(Type::None);
+		assert_eq!(ints.name(), "[i64]");
+		assert!(ints.is_value() && empty.is_value());
+		assert!(ints.contains(empty));
+		assert!(!empty.contains(ints));
+		assert_eq!(empty.sum(ints), ints);
+		assert!(matches!(ints.sum(Type::list(Type::Str)), Type::Sum(..)));
+	}
+
+	#[test]
+	fn invalid_operands() {
+		let store = Arena::get();
+		let lhs = code(Expr::Str("a"));
+		let rhs = code(Expr::Int(1));
+		let op = Operator {
+			op: Op::Add,
+			span: Span::empty(),
+		};
+		let add = code(Expr::Binary(op, store.store(lhs), store.store(rhs)));
+
+		let err = add.check_type(&mut TypeChecker::new()).unwrap_err();
+		assert!(err.to_string().contains("invalid operands for `+`: str and i64"));
+
+		let add = code(Expr::Binary(op, store.store(rhs), store.store(rhs)));
+		assert_eq!(add.check_type(&mut TypeChecker::new()).unwrap(), Type::Int);
+	}
+
+	#[test]
+	fn equality_operands() {
+		let op = |op| Operator {
+			op,
+			span: Span::empty(),
+		};
+		for typ in [Type::Bool, Type::Int, Type::Float, Type::Str] {
+			assert_eq!(op(Op::Eq).check_binary(typ, typ).unwrap(), Type::Bool);
+			assert_eq!(op(Op::Ne).check_binary(typ, typ).unwrap(), Type::Bool);
+		}
+
+		// lists and structs have no equality in either backend
+		let list = Type::list(Type::Int);
+		let err = op(Op::Eq).check_binary(list, list).unwrap_err();
+		assert!(err.to_string().contains("invalid operands for `==`: [i64] and [i64]"));
+	}
+
+	fn code(expr: Expr) -> Code {
+		Code {
+			expr,
+			span: Span::empty(),
+		}
+	}
+}
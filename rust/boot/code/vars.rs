@@ -0,0 +1,78 @@
+use super::*;
+
+/// Handle to a variable declaration.
+///
+/// Each declaration creates a new unique variable, so two declarations with
+/// the same name (e.g. shadowing) are different variables.
+#[derive(Copy, Clone)]
+pub struct Var {
+	data: &'static VarData,
+}
+
+struct VarData {
+	name: Symbol,
+	span: Span,
+	id: usize,
+}
+
+impl Var {
+	pub fn new(name: Symbol, span: Span) -> Self {
+		static COUNTER: AtomicUsize = AtomicUsize::new(0);
+		let id = COUNTER.fetch_add(1, Order::Relaxed) + 1;
+		let data = Arena::get().store(VarData { name, span, id });
+		Self { data }
+	}
+
+	pub fn name(&self) -> Symbol {
+		self.data.name
+	}
+
+	pub fn id(&self) -> usize {
+		self.data.id
+	}
+
+	fn as_ptr(&self) -> *const VarData {
+		self.data
+	}
+}
+
+impl HasSpan for Var {
+	fn span(&self) -> Span {
+		self.data.span
+	}
+}
+
+impl Eq for Var {}
+
+impl PartialEq for Var {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_ptr() == other.as_ptr()
+	}
+}
+
+impl Hash for Var {
+	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+		self.as_ptr().hash(state);
+	}
+}
+
+impl Display for Var {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "`")?;
+		self.name().write_name(f)?;
+		write!(f, "`")?;
+		Ok(())
+	}
+}
+
+impl Debug for Var {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "Var(")?;
+		self.name().write_name(f)?;
+		let span = self.span();
+		if !span.is_empty() {
+			write!(f, " @ {span}")?;
+		}
+		write!(f, ")")
+	}
+}
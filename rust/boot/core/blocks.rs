@@ -0,0 +1,170 @@
+use super::*;
+
+/// Indented block of lines following a `:` header line.
+///
+/// Blocks are nested as the last child of their header line, so block
+/// statements can find them at the end of their parent.
+#[derive(Debug)]
+pub struct Block;
+
+impl IsValue for Block {
+	fn is_scope(&self) -> bool {
+		true
+	}
+
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		Code::sequence(ctx, node.children())
+	}
+}
+
+/// Nests indented lines into [`Block`] nodes.
+///
+/// An indented block must follow a line ending with `:` and every dedent
+/// must return to the indentation of an enclosing block.
+#[derive(Debug)]
+pub struct ParseIndent;
+
+impl Eval for ParseIndent {
+	fn precedence(&self) -> Precedence {
+		Precedence::Indent
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let mut parents = Vec::new();
+		for it in nodes {
+			if let Some(parent) = it.parent() {
+				if !parents.contains(&parent) {
+					parents.push(parent);
+				}
+			}
+		}
+
+		for parent in parents {
+			let lines = parent.remove_nodes(..);
+			let mut stack = vec![Level::new(None)];
+			let mut last_line: Option<Node> = None;
+			for line in lines {
+				let first = if let Some(first) = first_token(line) {
+					first
+				} else {
+					// comments and other nodes don't affect indentation
+					stack.last_mut().unwrap().lines.push(line);
+					continue;
+				};
+
+				let level = first.span().location().indent();
+				let current = stack.last().unwrap();
+				let header = last_line.and_then(|x| block_header(x).map(|sep| (x, sep)));
+
+				if current.indent.is_none() || level > current.indent.unwrap() {
+					if current.indent.is_some() {
+						match header {
+							Some((header, _)) => stack.push(Level::new(Some(header))),
+							None => raise!(@first => "unexpected indentation"),
+						}
+					}
+					stack.last_mut().unwrap().indent = Some(level);
+				} else {
+					if let Some((_, sep)) = header {
+						raise!(@sep => "expected an indented block after `:`");
+					}
+
+					while level < stack.last().unwrap().indent.unwrap() {
+						if stack.len() == 1 {
+							break;
+						}
+						let block = stack.pop().unwrap();
+						block.close();
+					}
+
+					if stack.last().unwrap().indent != Some(level) {
+						let span = indent_span(first.span());
+						raise!(@span => "inconsistent indentation: dedent does not match any enclosing block");
+					}
+				}
+
+				stack.last_mut().unwrap().lines.push(line);
+				last_line = Some(line);
+			}
+
+			if let Some(sep) = last_line.and_then(block_header) {
+				raise!(@sep => "expected an indented block after `:`");
+			}
+
+			while stack.len() > 1 {
+				let block = stack.pop().unwrap();
+				block.close();
+			}
+
+			let root = stack.pop().unwrap();
+			parent.append_nodes(root.lines);
+		}
+		Ok(())
+	}
+}
+
+struct Level {
+	header: Option<Node>,
+	indent: Option<usize>,
+	lines: Vec<Node>,
+}
+
+impl Level {
+	fn new(header: Option<Node>) -> Self {
+		Self {
+			header,
+			indent: None,
+			lines: Vec::new(),
+		}
+	}
+
+	fn close(self) {
+		let header = self.header.unwrap();
+
+		// include nested blocks, since those are not part of the line span
+		let mut span = Span::for_range(&self.lines);
+		if let Some(last) = self.lines.last().and_then(|x| x.last()) {
+			span = span.merged(last.span());
+		}
+
+		let block = Node::new_at(Block, span);
+		block.set_done(true);
+		block.append_nodes(self.lines);
+		header.push_node(block);
+	}
+}
+
+/// First token of a raw line, ignoring comment-only lines.
+fn first_token(line: Node) -> Option<Token> {
+	let raw = line.cast::<Raw>()?;
+	let first = raw.list().first()?;
+	if let Token::Comment(..) = first {
+		None
+	} else {
+		Some(*first)
+	}
+}
+
+/// Returns the trailing `:` for a raw line that is still expecting a block.
+fn block_header(line: Node) -> Option<Token> {
+	if line.len() > 0 {
+		return None;
+	}
+
+	let symbols = Symbols::get();
+	let raw = line.cast::<Raw>()?;
+	let last = raw.list().iter().rev().find(|x| !matches!(x, Token::Comment(..)))?;
+	if last.symbol() == symbols.COLON {
+		Some(*last)
+	} else {
+		None
+	}
+}
+
+/// Span for the leading whitespace in the line of the given span.
+fn indent_span(span: Span) -> Span {
+	let src = span.source();
+	let text = &src.text()[..span.sta()];
+	let sta = text.rfind(['\r', '\n']).map(|x| x + 1).unwrap_or(0);
+	Span::new(src, sta, span.sta())
+}
@@ -0,0 +1,110 @@
+use super::*;
+
+/// Parenthesized expression.
+#[derive(Debug)]
+pub struct Paren;
+
+impl IsValue for Paren {
+	fn is_collection(&self) -> bool {
+		true
+	}
+
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		match children.len() {
+			0 => raise!(@node => "empty parenthesis"),
+			1 => children[0].compile(ctx),
+			_ => raise!(@node => "invalid parenthesis with multiple children:\n{node}"),
+		}
+	}
+}
+
+/// Square brackets, parsed as either a list literal or an index by
+/// [`ParseIndex`].
+#[derive(Debug)]
+pub struct Bracket;
+
+pub static BRACKETS: Bindings = Bindings::new();
+
+impl IsValue for Bracket {
+	fn is_collection(&self) -> bool {
+		true
+	}
+
+	fn bind(&self, node: Node) {
+		BRACKETS.add(node);
+	}
+}
+
+/// Matches bracket pairs within a parent node and nests their contents.
+#[derive(Debug)]
+pub struct ParseBrackets;
+
+impl Eval for ParseBrackets {
+	fn precedence(&self) -> Precedence {
+		Precedence::Brackets
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		let parens = (symbols.STA_PAREN, symbols.END_PAREN);
+		let pairs = [parens, (symbols.STA_BRACKET, symbols.END_BRACKET)];
+		let closing = |sta: Node| {
+			let symbol = sta.cast::<Token>().map(|x| x.symbol());
+			pairs.iter().find(|x| Some(x.0) == symbol).unwrap().1
+		};
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let parent = if let Some(parent) = it.parent() {
+				parent
+			} else {
+				continue;
+			};
+
+			let children = parent.remove_nodes(..);
+			let mut stack: Vec<(Option<Node>, Vec<Node>)> = vec![(None, Vec::new())];
+			for node in children {
+				let symbol = node.cast::<Token>().map(|x| x.symbol());
+				if pairs.iter().any(|x| Some(x.0) == symbol) {
+					node.set_done(true);
+					stack.push((Some(node), Vec::new()));
+				} else if let Some(&(_, end)) = pairs.iter().find(|x| Some(x.1) == symbol) {
+					node.set_done(true);
+					let (sta, list) = stack.pop().unwrap();
+					let sta = match sta.map(|x| (x, closing(x))) {
+						Some((sta, expected)) if expected == end => sta,
+						Some((sta, expected)) => {
+							let err = err!(@node => "expected `{}`, but found `{}`", expected.as_str(), end.as_str());
+							Err(err.with_label(sta, "opened here"))?
+						}
+						None => raise!(@node => "unmatched `{}`", end.as_str()),
+					};
+
+					let span = sta.span().merged(node.span());
+					let group = if end == symbols.END_PAREN {
+						let paren = Node::new_at(Paren, span);
+						paren.set_done(true);
+						paren
+					} else {
+						Node::new_at(Bracket, span)
+					};
+					group.append_nodes(list);
+					stack.last_mut().unwrap().1.push(group);
+				} else {
+					stack.last_mut().unwrap().1.push(node);
+				}
+			}
+
+			if let Some(sta) = stack.last().and_then(|x| x.0) {
+				raise!(@sta => "`{}` is not closed", sta.span().text());
+			}
+
+			let (_, list) = stack.pop().unwrap();
+			parent.append_nodes(list);
+		}
+		Ok(())
+	}
+}
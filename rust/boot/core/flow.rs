@@ -0,0 +1,480 @@
+use super::*;
+
+/// Conditional statement with the condition, the block, and an optional
+/// else branch as children.
+///
+/// The else branch is either a [`Block`] or a nested [`If`] for `elif`.
+#[derive(Debug)]
+pub struct If;
+
+impl IsValue for If {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 2 && children.len() != 3 {
+			raise!(@node => "invalid if statement with {} children", children.len());
+		}
+
+		let store = Arena::get();
+		let cond = children[0].compile(ctx)?;
+		let when_true = children[1].compile(ctx)?;
+		let otherwise = if let Some(node) = children.get(2) {
+			node.compile(ctx)?
+		} else {
+			let span = node.span();
+			Code {
+				expr: Expr::None,
+				span: Span::new(span.source(), span.end(), span.end()),
+			}
+		};
+
+		Ok(Code {
+			expr: Expr::If(store.store(cond), store.store(when_true), store.store(otherwise)),
+			span: node.span(),
+		})
+	}
+}
+
+/// Loop statement with the condition and the block as children.
+#[derive(Debug)]
+pub struct While;
+
+impl IsValue for While {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 2 {
+			raise!(@node => "invalid while statement with {} children", children.len());
+		}
+
+		let store = Arena::get();
+		let cond = children[0].compile(ctx)?;
+		let body = children[1].compile(ctx)?;
+		Ok(Code {
+			expr: Expr::While(store.store(cond), store.store(body)),
+			span: node.span(),
+		})
+	}
+}
+
+/// Range loop with the range expression and the block as children.
+#[derive(Debug)]
+pub struct For(pub Var);
+
+impl IsValue for For {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 2 {
+			raise!(@node => "invalid for loop with {} children", children.len());
+		}
+
+		// the range may be nested in groups or parenthesis
+		let mut expr = children[0];
+		while expr.len() == 1 && expr.value().is_collection() {
+			expr = expr.children()[0];
+		}
+
+		// any expression other than a range is a list
+		let store = Arena::get();
+		let range = match expr.cast::<Range>() {
+			Some(range) if expr.len() == 2 => range,
+			Some(..) => raise!(@expr => "invalid range with {} children", expr.len()),
+			None => {
+				let list = expr.compile(ctx)?;
+				let body = children[1].compile(ctx)?;
+				return Ok(Code {
+					expr: Expr::ForEach(self.0, store.store(list), store.store(body)),
+					span: node.span(),
+				});
+			}
+		};
+
+		let sta = expr.children()[0].compile(ctx)?;
+		let end = expr.children()[1].compile(ctx)?;
+		let body = children[1].compile(ctx)?;
+		Ok(Code {
+			expr: Expr::For(
+				self.0,
+				store.store(sta),
+				store.store(end),
+				range.inclusive,
+				store.store(body),
+			),
+			span: node.span(),
+		})
+	}
+}
+
+/// Integer range with the start and end as children.
+///
+/// Ranges are only supported as the expression of a [`For`] loop.
+#[derive(Debug)]
+pub struct Range {
+	pub inclusive: bool,
+}
+
+impl IsValue for Range {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let _ = ctx;
+		raise!(@node => "ranges are only supported in for loops")
+	}
+}
+
+/// Loop control statement.
+#[derive(Debug)]
+pub enum LoopControl {
+	Break,
+	Continue,
+}
+
+impl IsValue for LoopControl {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let _ = ctx;
+		let (name, expr) = match self {
+			LoopControl::Break => ("break", Expr::Break),
+			LoopControl::Continue => ("continue", Expr::Continue),
+		};
+
+		let mut parent = node.parent();
+		while let Some(it) = parent {
+			if it.cast::<FnDecl>().is_some() {
+				break;
+			}
+
+			if it.cast::<For>().is_some() || it.cast::<While>().is_some() {
+				return Ok(Code {
+					expr,
+					span: node.span(),
+				});
+			}
+			parent = it.parent();
+		}
+
+		raise!(@node => "`{name}` outside of a loop")
+	}
+}
+
+/// Parses `if cond:` blocks, including the `elif` and `else` lines that
+/// immediately follow them.
+#[derive(Debug)]
+pub struct ParseIf;
+
+impl Eval for ParseIf {
+	fn precedence(&self) -> Precedence {
+		Precedence::BlockParse
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let line = if let Some(line) = it.parent() {
+				line
+			} else {
+				continue;
+			};
+
+			let (cond, block) = parse_header(*it, true)?;
+			let mut branches = vec![(line.span(), cond, block)];
+			let mut otherwise = None;
+			while let Some(next) = line.next() {
+				let head = match next.first() {
+					Some(head) if !head.done() => head,
+					_ => break,
+				};
+
+				let symbol = head.cast::<Token>().map(|x| x.symbol());
+				if symbol == Some(symbols.ELIF) {
+					let (cond, block) = parse_header(head, true)?;
+					branches.push((next.span(), cond, block));
+					next.remove();
+				} else if symbol == Some(symbols.ELSE) {
+					let (_, block) = parse_header(head, false)?;
+					otherwise = Some(block);
+					next.remove();
+					break;
+				} else {
+					break;
+				}
+			}
+
+			let mut node = None;
+			for (span, cond, block) in branches.into_iter().rev() {
+				let end = node.or(otherwise).map(|x: Node| x.span()).unwrap_or(block.span());
+				let branch = Node::new_at(If, span.merged(end));
+				branch.set_done(true);
+				branch.append_nodes([cond, block]);
+				if let Some(otherwise) = node.or(otherwise) {
+					branch.push_node(otherwise);
+				}
+				node = Some(branch);
+			}
+
+			line.push_node(node.unwrap());
+		}
+		Ok(())
+	}
+}
+
+/// Reports `elif` and `else` lines without a preceding `if`.
+///
+/// Valid branches are consumed by [`ParseIf`], so this only waits while the
+/// previous line is still an unparsed `if` or `elif`.
+#[derive(Debug)]
+pub struct ParseElse;
+
+impl Eval for ParseElse {
+	fn precedence(&self) -> Precedence {
+		Precedence::BlockParse
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let name = it.cast::<Token>().map(|x| x.symbol().as_str()).unwrap_or_default();
+			let line = if let Some(line) = it.parent() {
+				line
+			} else {
+				continue;
+			};
+
+			if it.index() != 0 {
+				raise!(@it => "`{name}` must be at the start of a line");
+			}
+
+			let prev = line.prev().and_then(|x| x.first()).filter(|x| !x.done());
+			let prev = prev.and_then(|x| x.cast::<Token>()).map(|x| x.symbol());
+			if prev == Some(symbols.IF) || prev == Some(symbols.ELIF) {
+				continue;
+			}
+
+			raise!(@it => "`{name}` without a matching `if`");
+		}
+		Ok(())
+	}
+}
+
+/// Parses `while cond:` blocks.
+#[derive(Debug)]
+pub struct ParseWhile;
+
+impl Eval for ParseWhile {
+	fn precedence(&self) -> Precedence {
+		Precedence::BlockParse
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let line = if let Some(line) = it.parent() {
+				line
+			} else {
+				continue;
+			};
+
+			let (cond, block) = parse_header(*it, true)?;
+			let node = Node::new_at(While, line.span().merged(block.span()));
+			node.set_done(true);
+			node.append_nodes([cond, block]);
+			line.push_node(node);
+		}
+		Ok(())
+	}
+}
+
+/// Parses `for name in range:` blocks.
+///
+/// The loop variable is bound only within the loop block.
+#[derive(Debug)]
+pub struct ParseFor;
+
+impl Eval for ParseFor {
+	fn precedence(&self) -> Precedence {
+		Precedence::BlockParse
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let line = if let Some(line) = it.parent() {
+				line
+			} else {
+				continue;
+			};
+
+			let (expr, block) = parse_header(*it, true)?;
+
+			let name = match expr.first().and_then(|x| x.cast::<Token>()) {
+				Some(&Token::Word(name, ..)) => name,
+				_ => raise!(@expr => "expected a loop variable after `for`"),
+			};
+
+			let has_in = expr.node(1).and_then(|x| x.cast::<Token>()).map(|x| x.symbol()) == Some(symbols.IN);
+			if !has_in {
+				raise!(@expr => "expected `in` after the loop variable");
+			}
+
+			if expr.len() == 2 {
+				raise!(@expr => "missing range or list for `for` loop");
+			}
+
+			let head = expr.remove_nodes(..2);
+			for it in head {
+				it.set_done(true);
+			}
+
+			let var = Var::new(name, head[0].span());
+			let node = Node::new_at(For(var), line.span().merged(block.span()));
+			node.set_done(true);
+			node.append_nodes([expr, block]);
+			line.push_node(node);
+
+			WORDS.get(name).set_span(block.span(), BindVar(var, node));
+		}
+		Ok(())
+	}
+}
+
+/// Parses a `..` or `..=` range operator.
+#[derive(Debug)]
+pub struct ParseRange;
+
+impl Eval for ParseRange {
+	fn precedence(&self) -> Precedence {
+		Precedence::OpRange
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let parent = if let Some(parent) = it.parent() {
+				parent
+			} else {
+				continue;
+			};
+
+			let symbol = it.cast::<Token>().map(|x| x.symbol());
+			let index = it.index();
+			if index == 0 {
+				raise!(@it => "missing start for range");
+			} else if index == parent.len() - 1 {
+				raise!(@it => "missing end for range");
+			}
+
+			it.set_done(true);
+			let nodes = parent.remove_nodes(..);
+			for it in nodes.range(index + 1..) {
+				let symbol = it.cast::<Token>().map(|x| x.symbol());
+				if symbol == Some(symbols.RANGE) || symbol == Some(symbols.RANGE_INCLUSIVE) {
+					raise!(@it => "ranges cannot be chained");
+				}
+			}
+
+			let inclusive = symbol == Some(symbols.RANGE_INCLUSIVE);
+			let node = Node::new_at(Range { inclusive }, nodes.span());
+			node.set_done(true);
+			for range in [nodes.range(..index), nodes.range(index + 1..)] {
+				let group = Node::new_at(Group, range.span());
+				group.set_done(true);
+				group.append_nodes(range);
+				node.push_node(group);
+			}
+			parent.push_node(node);
+		}
+		Ok(())
+	}
+}
+
+/// Parses `break` and `continue` statements.
+#[derive(Debug)]
+pub struct ParseLoopControl;
+
+impl Eval for ParseLoopControl {
+	fn precedence(&self) -> Precedence {
+		Precedence::BlockParse
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let symbol = it.cast::<Token>().map(|x| x.symbol()).unwrap_or_default();
+			if it.index() != 0 || it.next().is_some() {
+				raise!(@it => "`{}` must be on a line by itself", symbol.as_str());
+			}
+
+			let value = if symbol == symbols.BREAK {
+				LoopControl::Break
+			} else {
+				LoopControl::Continue
+			};
+
+			it.set_done(true);
+			let node = Node::new_at(value, it.span());
+			node.set_done(true);
+			it.replace([node]);
+		}
+		Ok(())
+	}
+}
+
+/// Parses a `keyword expr: block` line, removing all of its nodes.
+///
+/// Returns the expression between the keyword and the `:` as a [`Group`]
+/// together with the indented block.
+pub(crate) fn parse_header(keyword: Node, with_expr: bool) -> Result<(Node, Node)> {
+	let symbols = Symbols::get();
+	let name = keyword.cast::<Token>().map(|x| x.symbol().as_str()).unwrap_or_default();
+	if keyword.index() != 0 {
+		raise!(@keyword => "`{name}` must be at the start of a line");
+	}
+
+	let line = keyword.parent().unwrap();
+	let nodes = line.remove_nodes(..);
+	let len = nodes.len();
+
+	let block = nodes.get(len - 1).filter(|x| x.cast::<Block>().is_some());
+	let sep = nodes.get(len.saturating_sub(2)).filter(|x| {
+		let symbol = x.cast::<Token>().map(|x| x.symbol());
+		len > 2 && symbol == Some(symbols.COLON)
+	});
+
+	let (sep, block) = match (sep, block) {
+		(Some(sep), Some(block)) => (sep, block),
+		_ => raise!(@keyword => "expected `:` followed by an indented block for `{name}`"),
+	};
+
+	keyword.set_done(true);
+	sep.set_done(true);
+
+	let expr = nodes.range(1..len - 2);
+	if with_expr && expr.len() == 0 {
+		raise!(@keyword => "missing condition for `{name}`");
+	} else if !with_expr && expr.len() > 0 {
+		raise!(@expr.span() => "unexpected expression after `{name}`");
+	}
+
+	let group = Node::new_at(Group, expr.span());
+	group.set_done(true);
+	group.append_nodes(expr);
+	Ok((group, block))
+}
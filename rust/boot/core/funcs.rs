@@ -0,0 +1,331 @@
+use super::*;
+
+/// Function declaration with the function body as the single child.
+#[derive(Debug)]
+pub struct FnDecl(pub Function);
+
+impl IsValue for FnDecl {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 1 {
+			raise!(@node => "invalid function declaration with {} children", children.len());
+		}
+
+		let body = children[0].compile(ctx)?;
+		self.0.set_body(body)?;
+
+		// the declaration itself has no effect, the body runs on each call
+		Ok(Code {
+			expr: Expr::None,
+			span: node.span(),
+		})
+	}
+}
+
+/// Function call with one child for each argument.
+#[derive(Debug)]
+pub struct Call(pub Function);
+
+impl IsValue for Call {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let func = self.0;
+		let params = func.params().len();
+		let args = node.children();
+		if args.len() != params {
+			let s = if params == 1 { "" } else { "s" };
+			let err = err!(@node => "function {func} expects {params} argument{s}, but got {}", args.len());
+			Err(err.with_label(func, "function declared here"))?;
+		}
+
+		let args = Code::list(ctx, args)?;
+		Ok(Code {
+			expr: Expr::Call(func, args),
+			span: node.span(),
+		})
+	}
+}
+
+/// Return statement with the optional return value as the single child.
+#[derive(Debug)]
+pub struct Return;
+
+impl IsValue for Return {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		if FnDecl::owner(node).is_none() {
+			raise!(@node => "`return` outside of a function");
+		}
+
+		let value = match node.first() {
+			Some(value) => value.compile(ctx)?,
+			None => Code {
+				expr: Expr::None,
+				span: node.span(),
+			},
+		};
+
+		Ok(Code {
+			expr: Expr::Return(Arena::get().store(value)),
+			span: node.span(),
+		})
+	}
+}
+
+impl FnDecl {
+	/// Returns the innermost function declaration containing the node.
+	pub fn owner(node: Node) -> Option<Node> {
+		let mut parent = node.parent();
+		while let Some(it) = parent {
+			if it.cast::<FnDecl>().is_some() {
+				return Some(it);
+			}
+			parent = it.parent();
+		}
+		None
+	}
+}
+
+/// Parses `fn name(params):` blocks.
+///
+/// The function name is bound to the entire enclosing scope, so functions
+/// can be called before their declaration and recursively.
+#[derive(Debug)]
+pub struct ParseFn;
+
+impl Eval for ParseFn {
+	fn precedence(&self) -> Precedence {
+		Precedence::BlockParse
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let line = if let Some(line) = it.parent() {
+				line
+			} else {
+				continue;
+			};
+
+			let (head, block) = parse_header(*it, true)?;
+			let name = match head.first().and_then(|x| x.cast::<Token>()) {
+				Some(&Token::Word(name, ..)) => name,
+				_ => raise!(@head => "expected a function name after `fn`"),
+			};
+
+			let params = match head.node(1) {
+				Some(params) if head.len() == 2 && params.cast::<Paren>().is_some() => params,
+				_ => raise!(@head => "expected a parameter list after the function name"),
+			};
+
+			let mut vars = Vec::new();
+			for (n, arg) in split_list(params, symbols.COMMA).into_iter().enumerate() {
+				let param = match arg.as_slice() {
+					[param] => *param,
+					_ => raise!(@arg.span() => "invalid parameter #{} for function", n + 1),
+				};
+
+				let param_name = match param.cast::<Token>() {
+					Some(&Token::Word(name, ..)) => name,
+					_ => raise!(@param => "parameter must be a name"),
+				};
+
+				if let Some(prev) = vars.iter().find(|x: &&Var| x.name() == param_name) {
+					let err = err!(@param => "duplicate parameter `{}`", param_name.as_str());
+					Err(err.with_label(prev, "first declared here"))?;
+				}
+
+				param.set_done(true);
+				vars.push(Var::new(param_name, param.span()));
+			}
+
+			for it in head.children() {
+				it.set_done(true);
+			}
+
+			for var in vars.iter() {
+				WORDS.get(var.name()).set_span(block.span(), BindVar(*var, block));
+			}
+
+			let scope = if let Some(scope) = line.scope() {
+				scope.span()
+			} else {
+				line.span()
+			};
+
+			let name_span = head.children()[0].span();
+			declare_in_scope(scope, name, name_span)?;
+
+			let func = Function::new(name, name_span, vars);
+			let node = Node::new_at(FnDecl(func), line.span().merged(block.span()));
+			node.set_done(true);
+			node.push_node(block);
+			line.push_node(node);
+			WORDS.get(name).set_span(scope, BindFn(func));
+		}
+		Ok(())
+	}
+}
+
+/// Binds function names to their declaration, parsing calls.
+#[derive(Debug)]
+pub struct BindFn(pub Function);
+
+impl Eval for BindFn {
+	fn precedence(&self) -> Precedence {
+		Precedence::VarBinding
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let func = self.0;
+
+			// declared operators call the function with their operands
+			if let Some(op) = it.parent().filter(|x| x.cast::<ApplyOperator>().is_some()) {
+				it.set_done(true);
+				let args = op.remove_nodes(1..);
+				let node = Build::new(Call(func)).at(op.span()).children(args).node();
+				op.replace([node]);
+				continue;
+			}
+
+			let args = match it.next() {
+				Some(args) if args.cast::<Paren>().is_some() => args,
+				_ => raise!(@it => "function {func} must be called with a list of arguments"),
+			};
+
+			it.set_done(true);
+
+			let mut list = Vec::new();
+			for (n, arg) in split_list(args, symbols.COMMA).into_iter().enumerate() {
+				if arg.len() == 0 {
+					raise!(@arg.span() => "missing argument #{} for {func}", n + 1);
+				}
+
+				let group = Node::new_at(Group, arg.span());
+				group.set_done(true);
+				group.append_nodes(arg);
+				list.push(group);
+			}
+
+			args.remove();
+
+			let node = Node::new_at(Call(func), it.span().merged(args.span()));
+			node.set_done(true);
+			node.append_nodes(list);
+			it.replace([node]);
+		}
+		Ok(())
+	}
+}
+
+/// Parses `return` statements.
+#[derive(Debug)]
+pub struct ParseReturn;
+
+impl Eval for ParseReturn {
+	fn precedence(&self) -> Precedence {
+		Precedence::BlockParse
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let parent = if let Some(parent) = it.parent() {
+				parent
+			} else {
+				continue;
+			};
+
+			if it.index() != 0 {
+				raise!(@it => "`return` must be at the start of a line");
+			}
+
+			it.set_done(true);
+			let nodes = parent.remove_nodes(..);
+			let node = Node::new_at(Return, nodes.span());
+			node.set_done(true);
+
+			let value = nodes.range(1..);
+			if value.len() > 0 {
+				let group = Node::new_at(Group, value.span());
+				group.set_done(true);
+				group.append_nodes(value);
+				node.push_node(group);
+			}
+			parent.push_node(node);
+		}
+		Ok(())
+	}
+}
+
+/// Splits the children of the node at each separator, removing them.
+///
+/// An empty node returns an empty list.
+fn split_list(node: Node, separator: Symbol) -> Vec<NodeList> {
+	let nodes = node.remove_nodes(..);
+	let mut output = Vec::new();
+	if nodes.len() == 0 {
+		return output;
+	}
+
+	let mut sta = 0;
+	for (n, it) in nodes.into_iter().enumerate() {
+		if it.cast::<Token>().map(|x| x.symbol()) == Some(separator) {
+			it.set_done(true);
+			output.push(nodes.range(sta..n));
+			sta = n + 1;
+		}
+	}
+	output.push(nodes.range(sta..));
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn duplicate_functions() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("dup", "fn f():\n\treturn 1\nfn f():\n\treturn 2\nf()");
+		let err = Session::new().execute(&[src]).unwrap_err().to_string();
+		assert!(err.contains("`f` is already declared in this scope"), "{err}");
+		assert!(err.contains("first declared here"), "{err}");
+
+		// functions in a nested scope shadow the outer ones
+		let src = sources.from_string("nested", "fn f():\n\treturn 1\nif true:\n\tfn f():\n\t\treturn 2\nf()");
+		let value = Session::new().execute(&[src])?;
+		assert_eq!(value.cast::<i64>(), Some(&1));
+		Ok(())
+	}
+
+	#[test]
+	fn stack_overflow() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let text = "fn deep(n):\n\tif n == 0:\n\t\treturn 0\n\treturn 1 + deep(n - 1)\n";
+		let src = sources.from_string("shallow", format!("{text}deep(100)"));
+		let value = Session::new().execute(&[src])?;
+		assert_eq!(value.cast::<i64>(), Some(&100));
+
+		// the runtime fails before the thread runs out of stack
+		let src = sources.from_string("deep", format!("{text}deep(1000000000)"));
+		let err = Session::new().execute(&[src]).unwrap_err().to_string();
+		assert!(err.contains("stack overflow calling `deep`"), "{err}");
+		Ok(())
+	}
+}
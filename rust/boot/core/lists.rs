@@ -0,0 +1,245 @@
+use super::*;
+
+/// List literal with one child for each item.
+#[derive(Debug)]
+pub struct ListLiteral;
+
+impl IsValue for ListLiteral {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let items = Code::list(ctx, node.children())?;
+		Ok(Code {
+			expr: Expr::List(items),
+			span: node.span(),
+		})
+	}
+}
+
+/// List indexing with the list and the index as children.
+#[derive(Debug)]
+pub struct Index;
+
+impl IsValue for Index {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 2 {
+			raise!(@node => "invalid index with {} children", children.len());
+		}
+
+		let store = Arena::get();
+		let list = children[0].compile(ctx)?;
+		let index = children[1].compile(ctx)?;
+		Ok(Code {
+			expr: Expr::Index(store.store(list), store.store(index)),
+			span: node.span(),
+		})
+	}
+}
+
+/// Builtin function call with one child for each argument.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Builtin {
+	/// `len(list)` returns the number of items in the list.
+	Len,
+	/// `append(list, item)` adds the item to the end of the list.
+	Append,
+}
+
+impl Builtin {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Builtin::Len => "len",
+			Builtin::Append => "append",
+		}
+	}
+
+	pub fn params(&self) -> usize {
+		match self {
+			Builtin::Len => 1,
+			Builtin::Append => 2,
+		}
+	}
+}
+
+impl IsValue for Builtin {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let args = Code::list(ctx, node.children())?;
+		let store = Arena::get();
+		let expr = match (self, args) {
+			(Builtin::Len, [list]) => Expr::Len(store.store(*list)),
+			(Builtin::Append, [list, item]) => Expr::Append(store.store(*list), store.store(*item)),
+			_ => raise!(@node => "invalid call to `{}` with {} arguments", self.name(), args.len()),
+		};
+		Ok(Code {
+			expr,
+			span: node.span(),
+		})
+	}
+}
+
+/// Parses calls to a builtin function.
+///
+/// This runs after the declared names are bound, so a function or variable
+/// with the same name takes precedence.
+#[derive(Debug)]
+pub struct ParseBuiltin(pub Builtin);
+
+impl Eval for ParseBuiltin {
+	fn precedence(&self) -> Precedence {
+		Precedence::Builtin
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let builtin = self.0;
+		let name = builtin.name();
+		let pattern = Pattern::new([Match::AnyWord, Match::value::<Paren>()]);
+		for it in nodes {
+			if it.done() || it.parent().is_none() {
+				continue;
+			}
+
+			let found = match pattern.match_at(*it) {
+				Some(found) => found,
+				None => raise!(@it => "`{name}` must be called with a list of arguments"),
+			};
+
+			let comma = Match::symbol(",");
+			let items = found.node(1).children();
+			let args = items.split(comma);
+			for (n, arg) in args.iter().enumerate() {
+				if arg.len() == 0 {
+					raise!(@arg.span_or_pos() => "missing argument #{} for `{name}`", n + 1);
+				}
+			}
+
+			let params = builtin.params();
+			if args.len() != params {
+				let s = if params == 1 { "" } else { "s" };
+				raise!(@found.span() => "`{name}` expects {params} argument{s}, but got {}", args.len());
+			}
+
+			for it in items.into_iter().filter(|x| comma.matches(*x)) {
+				it.set_done(true);
+			}
+
+			found.set_done();
+			found.remove();
+			found.node(1).remove_nodes(..);
+
+			let node = Build::new(builtin).at(found.span());
+			let node = args.into_iter().fold(node, |node, arg| node.group(arg));
+			found.insert([node.node()]);
+		}
+		Ok(())
+	}
+}
+
+/// Parses square brackets as an index if they follow an operand, such as
+/// `list[0]`, or as a list literal otherwise.
+#[derive(Debug)]
+pub struct ParseIndex;
+
+impl Eval for ParseIndex {
+	fn precedence(&self) -> Precedence {
+		Precedence::Index
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			parse_bracket(*it)?;
+		}
+		Ok(())
+	}
+}
+
+/// Returns the operand before a bracket or a [`Member`], if any.
+///
+/// Brackets and members are parsed from left to right, so in `a[0].b` the
+/// member applies to the result of `a[0]`.
+pub(crate) fn postfix_operand(node: Node) -> Result<Option<Node>> {
+	if let Some(prev) = node.prev() {
+		if prev.cast::<Bracket>().is_some() {
+			parse_bracket(prev)?;
+		} else if prev.cast::<Member>().is_some() {
+			parse_member(prev)?;
+		}
+	}
+
+	let is_operand = |x: &Node| x.cast::<Token>().is_none() && x.cast::<OperatorRef>().is_none();
+	Ok(node.prev().filter(is_operand))
+}
+
+fn parse_bracket(node: Node) -> Result<()> {
+	if node.done() || node.parent().is_none() {
+		return Ok(());
+	}
+
+	let prev = postfix_operand(node)?;
+	node.set_done(true);
+
+	let comma = Match::symbol(",");
+	let items = node.remove_nodes(..);
+	let parts = items.split(comma);
+	for it in items.into_iter().filter(|x| comma.matches(*x)) {
+		it.set_done(true);
+	}
+
+	match prev {
+		Some(list) => {
+			let index = match parts.as_slice() {
+				[index] if index.len() > 0 => *index,
+				[] => raise!(@node => "missing index for `[]`"),
+				_ => raise!(@node => "expected a single index"),
+			};
+
+			let pattern = Pattern::new([Match::Any, Match::value::<Bracket>()]);
+			let found = pattern.match_at(list).unwrap();
+			found.remove();
+			let index = Build::new(Index).at(found.span()).child(list).group(index);
+			found.insert([index.node()]);
+		}
+		None => {
+			for (n, it) in parts.iter().enumerate() {
+				if it.len() == 0 {
+					raise!(@it.span_or_pos() => "missing list item #{}", n + 1);
+				}
+			}
+
+			let list = Build::new(ListLiteral).at(node.span());
+			let list = parts.into_iter().fold(list, |list, it| list.group(it));
+			node.replace([list.node()]);
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn empty_list_item_type() -> Result<()> {
+		let _lock = init_test();
+
+		// aliases of an empty list share its item type
+		let sources = SourceMap::new(".")?;
+		let text = "let xs = []\nlet ys = xs\nappend(ys, 1)\nappend(xs, \"a\")\nfor y in ys:\n\tprint y + 1";
+		let err = Session::new()
+			.execute(&[sources.from_string("alias", text)])
+			.unwrap_err()
+			.to_string();
+		assert!(err.contains("cannot append str to a list of i64"), "{err}");
+
+		// as do function parameters
+		let text = "fn f(l):\n\tappend(l, \"a\")\nlet xs = []\nf(xs)\nappend(xs, 1)\nprint xs";
+		let err = Session::new()
+			.execute(&[sources.from_string("param", text)])
+			.unwrap_err()
+			.to_string();
+		assert!(err.contains("cannot append i64 to a list of str"), "{err}");
+
+		let text = "let xs = []\nlet ys = xs\nappend(ys, 20)\nappend(xs, 22)\nys[0] + xs[1]";
+		let value = Session::new().execute(&[sources.from_string("valid", text)])?;
+		assert_eq!(value.cast::<i64>(), Some(&42));
+		Ok(())
+	}
+}
@@ -45,10 +45,14 @@ impl Eval for ParseLiteral {
 							continue;
 						}
 					}
-					Token::Integer(span) => {
-						let value = parse_int(span)?;
-						Literal::Int(value)
-					}
+					Token::Integer(span) => match parse_int(span)? {
+						Some(value) => Literal::Int(value),
+						None => {
+							warning!(@span => "integer literal is too large for i64, using f64");
+							let value = parse_float(span)?;
+							Literal::Float(value)
+						}
+					},
 					Token::Float(span) => {
 						let value = parse_float(span)?;
 						Literal::Float(value)
@@ -69,7 +73,93 @@ impl Eval for ParseLiteral {
 	}
 }
 
-fn parse_int(span: &Span) -> Result<i64> {
+/// Interpolated string, with a child for each text piece or expression.
+#[derive(Debug)]
+pub struct Interpolation;
+
+impl IsValue for Interpolation {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let parts = Code::list(ctx, node.children())?;
+		Ok(Code {
+			expr: Expr::Concat(parts),
+			span: node.span(),
+		})
+	}
+}
+
+/// Expands `"{expr}"` string literals into an [`Interpolation`].
+///
+/// This runs before any other parsing, so the tokens for each interpolated
+/// expression are parsed just like the tokens around the string.
+pub struct ParseInterpolation(pub Lexer);
+
+impl Debug for ParseInterpolation {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "ParseInterpolation")
+	}
+}
+
+impl Eval for ParseInterpolation {
+	fn precedence(&self) -> Precedence {
+		Precedence::Interpolation
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let span = match it.cast::<Token>() {
+				Some(Token::Literal(span)) => *span,
+				_ => continue,
+			};
+
+			let parts = parse_str_parts(&span)?;
+			if !parts.iter().any(|x| matches!(x, StrPart::Expr(..))) {
+				continue;
+			}
+
+			let mut list = Vec::new();
+			for part in parts {
+				let node = match part {
+					StrPart::Text(text) => {
+						// text spans are only approximate, so use the entire literal
+						let node = Node::new_at(Literal::Str(Arena::get().str(text)), span);
+						node.set_done(true);
+						node
+					}
+					StrPart::Expr(expr) => {
+						let mut cursor = Cursor::for_span(expr);
+						let tokens = self.0.tokenize(&mut cursor)?;
+						for token in tokens.iter() {
+							if let Token::Break(..) | Token::Comment(..) = token {
+								raise!(@token.span() => "invalid {token} in string interpolation");
+							}
+						}
+
+						let group = Node::new_at(Group, expr);
+						group.set_done(true);
+						group.append_nodes(tokens.into_iter().map(Node::new));
+						group
+					}
+				};
+				list.push(node);
+			}
+
+			it.set_done(true);
+			let node = Node::new_at(Interpolation, span);
+			node.set_done(true);
+			node.append_nodes(list);
+			it.replace([node]);
+		}
+		Ok(())
+	}
+}
+
+/// Parses an integer literal, returning none if a decimal literal does not
+/// fit in an i64.
+fn parse_int(span: &Span) -> Result<Option<i64>> {
 	let text = span.text();
 	let (text, base) = if text.starts_with("0x") || text.starts_with("0X") {
 		let text = &text[2..];
@@ -84,7 +174,11 @@ fn parse_int(span: &Span) -> Result<i64> {
 		(text, 10)
 	};
 
-	parse_digits(text, base, span)
+	match parse_digits(text, base, span) {
+		Ok(value) => Ok(Some(value)),
+		Err(_) if base == 10 && text.chars().all(|x| x == '_' || is_digit(x)) => Ok(None),
+		Err(err) => Err(err),
+	}
 }
 
 fn parse_float(span: &Span) -> Result<f64> {
@@ -140,25 +234,228 @@ fn parse_float(span: &Span) -> Result<f64> {
 		Err(err) => raise!(@span => "invalid floating point literal ({err} -- {num})"),
 	};
 
+	if value.is_infinite() {
+		warning!(@span => "floating point literal is out of range for f64");
+	}
+
 	Ok(value)
 }
 
-fn parse_str(span: &Span) -> Result<&'static str> {
+pub(crate) fn parse_str(span: &Span) -> Result<&'static str> {
+	let mut output = String::new();
+	for it in parse_str_parts(span)? {
+		match it {
+			StrPart::Text(text) => output.push_str(&text),
+			StrPart::Expr(span) => raise!(@span => "unexpected interpolation in string literal"),
+		}
+	}
+	Ok(Arena::get().str(output))
+}
+
+/// Piece of a string literal, either decoded text or the span for an
+/// interpolated expression.
+pub(crate) enum StrPart {
+	Text(String),
+	Expr(Span),
+}
+
+/// Decodes a string literal into its parts.
+///
+/// Only double-quoted strings support `{expr}` interpolation, with `{{` and
+/// `}}` for literal braces. Raw strings are used as is, and triple-quoted
+/// strings have their common indentation removed.
+pub(crate) fn parse_str_parts(span: &Span) -> Result<Vec<StrPart>> {
 	let text = span.text();
-	let delim = if text.starts_with('\'') {
-		"'"
-	} else if text.starts_with('\"') {
-		"\""
-	} else {
-		raise!(@span => "invalid delimited string literal")
+	let (open, delim, raw) = match string_delimiter(text) {
+		Some(delim) => delim,
+		None => raise!(@span => "invalid delimited string literal"),
 	};
 
-	if !text.ends_with(delim) {
+	if text.len() < open + delim.len() || !text.ends_with(delim) {
 		raise!(@span => "string missing end `{delim}` delimiter");
 	};
 
-	let text = &text[delim.len()..text.len() - delim.len()];
-	Ok(text)
+	let sta = span.sta() + open;
+	let text = &text[open..text.len() - delim.len()];
+	let segments = if delim.len() > 1 {
+		dedent(text, sta)
+	} else {
+		vec![(sta, text)]
+	};
+
+	let mut output = Vec::new();
+	let mut current = String::new();
+	for (sta, text) in segments {
+		if raw {
+			current.push_str(text);
+		} else {
+			let interpolate = delim != "'";
+			decode_str(span.source(), sta, text, interpolate, &mut current, &mut output)?;
+		}
+	}
+
+	if !current.is_empty() || output.is_empty() {
+		output.push(StrPart::Text(current));
+	}
+	Ok(output)
+}
+
+/// Decodes escapes and interpolations for a piece of string literal text
+/// starting at the `sta` offset in the source.
+fn decode_str(
+	src: Source,
+	sta: usize,
+	text: &str,
+	interpolate: bool,
+	current: &mut String,
+	output: &mut Vec<StrPart>,
+) -> Result<()> {
+	let mut chars = text.char_indices().peekable();
+	while let Some((pos, chr)) = chars.next() {
+		let at = |len: usize| Span::new(src, sta + pos, sta + pos + len);
+		match chr {
+			'\\' => {
+				let (next, len) = match chars.next() {
+					Some((_, next)) => (next, 1 + next.len_utf8()),
+					None => raise!(@at(1) => "missing escape sequence after `\\`"),
+				};
+				let decoded = match next {
+					'n' => '\n',
+					't' => '\t',
+					'r' => '\r',
+					'0' => '\0',
+					'\\' | '\'' | '"' => next,
+					'x' => {
+						let digits = text[pos + len..]
+							.get(..2)
+							.filter(|x| x.chars().all(|c| c.is_ascii_hexdigit()));
+						let code = match digits {
+							Some(digits) => u32::from_str_radix(digits, 16).unwrap(),
+							None => raise!(@at(len) => "invalid escape, `\\x` must be followed by two hex digits"),
+						};
+						if code > 0x7F {
+							raise!(@at(len + 2) => "invalid escape, `\\x` must be at most `\\x7F`");
+						}
+						advance_by(&mut chars, 2);
+						char::from_u32(code).unwrap()
+					}
+					'u' => {
+						let rest = &text[pos + len..];
+						let digits = rest.strip_prefix('{').and_then(|x| x.find('}').map(|end| &x[..end]));
+						let digits = match digits {
+							Some(digits) => digits,
+							None => raise!(@at(len) => "invalid escape, `\\u` must be followed by `{{...}}`"),
+						};
+
+						let valid = digits.len() <= 6 && digits.chars().all(|c| c.is_ascii_hexdigit());
+						let code = u32::from_str_radix(digits, 16).ok().filter(|_| valid);
+						match code.and_then(char::from_u32) {
+							Some(chr) => {
+								advance_by(&mut chars, digits.chars().count() + 2);
+								chr
+							}
+							None => raise!(@at(len + digits.len() + 2) => "invalid unicode escape `\\u{{{digits}}}`"),
+						}
+					}
+					_ => raise!(@at(len) => "unknown escape sequence `\\{next}`"),
+				};
+				current.push(decoded);
+			}
+			'{' if interpolate => {
+				if let Some((_, '{')) = chars.peek() {
+					chars.next();
+					current.push('{');
+					continue;
+				}
+
+				let expr_sta = pos + 1;
+				let expr_end = loop {
+					match chars.next() {
+						Some((end, '}')) => break end,
+						Some(..) => {}
+						None => raise!(@at(1) => "missing `}}` for string interpolation"),
+					}
+				};
+
+				if !current.is_empty() {
+					output.push(StrPart::Text(std::mem::take(current)));
+				}
+				let expr = Span::new(src, sta + expr_sta, sta + expr_end);
+				if expr.text().trim().is_empty() {
+					raise!(@at(expr_end - pos + 1) => "empty expression in string interpolation");
+				}
+				output.push(StrPart::Expr(expr));
+			}
+			'}' if interpolate => {
+				if let Some((_, '}')) = chars.peek() {
+					chars.next();
+					current.push('}');
+				} else {
+					raise!(@at(1) => "unmatched `}}` in string, use `}}}}` for a literal brace");
+				}
+			}
+			_ => current.push(chr),
+		}
+	}
+
+	Ok(())
+}
+
+/// Splits the text of a triple-quoted string into lines with their source
+/// offset, without the common indentation.
+///
+/// Blank lines right after the opening and before the closing delimiter
+/// are not part of the string.
+fn dedent(text: &str, sta: usize) -> Vec<(usize, &str)> {
+	let mut lines = Vec::new();
+	let mut pos = 0;
+	for line in text.split('\n') {
+		lines.push((pos, line.strip_suffix('\r').unwrap_or(line)));
+		pos += line.len() + 1;
+	}
+
+	if lines.len() > 1 && lines[0].1.trim().is_empty() {
+		lines.remove(0);
+	}
+
+	if lines.len() > 1 && lines[lines.len() - 1].1.trim().is_empty() {
+		lines.pop();
+	}
+
+	let mut indent: Option<&str> = None;
+	for (_, line) in lines.iter().filter(|x| !x.1.trim().is_empty()) {
+		let prefix = &line[..line.len() - line.trim_start().len()];
+		let common = match indent {
+			Some(indent) => {
+				let common = indent.chars().zip(prefix.chars()).take_while(|(a, b)| a == b);
+				common.map(|(a, _)| a.len_utf8()).sum()
+			}
+			None => prefix.len(),
+		};
+		indent = Some(&prefix[..common]);
+	}
+
+	let indent = indent.map(|x| x.len()).unwrap_or(0);
+	let mut output = Vec::new();
+	for (n, (pos, line)) in lines.iter().copied().enumerate() {
+		if n > 0 {
+			// the line break is always before the line in the source
+			let pos = pos - 1;
+			output.push((sta + pos, &text[pos..pos + 1]));
+		}
+
+		if line.trim().is_empty() {
+			continue;
+		}
+		output.push((sta + pos + indent, &line[indent..]));
+	}
+	output
+}
+
+fn advance_by<T: Iterator>(iter: &mut T, count: usize) {
+	for _ in 0..count {
+		iter.next();
+	}
 }
 
 fn parse_digits(text: &str, base: i64, span: &Span) -> Result<i64> {
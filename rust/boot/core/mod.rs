@@ -1,18 +1,38 @@
 use super::*;
 
+mod blocks;
+mod brackets;
+mod flow;
+mod funcs;
 mod group;
 mod lines;
+mod lists;
 mod literal;
+mod modules;
+mod operators;
+mod ops;
 mod print;
 mod program;
 mod raw;
+mod structs;
+mod vars;
 
+pub use blocks::*;
+pub use brackets::*;
+pub use flow::*;
+pub use funcs::*;
 pub use group::*;
 pub use lines::*;
+pub use lists::*;
 pub use literal::*;
+pub use modules::*;
+pub use operators::*;
+pub use ops::*;
 pub use print::*;
 pub use program::*;
 pub use raw::*;
+pub use structs::*;
+pub use vars::*;
 
 #[derive(Debug)]
 pub struct Global<T: Eval>(T);
@@ -46,3 +66,23 @@ impl Eval for RemoveNode {
 		Ok(())
 	}
 }
+
+/// Declares a name bound to an entire scope, such as a function or a
+/// struct, which must be unique within the scope.
+///
+/// Declarations in the same scope bind the name with the same span, so
+/// without this check one of them would silently win.
+pub fn declare_in_scope(scope: Span, name: Symbol, span: Span) -> Result<()> {
+	static DECLARED: Init<Mutex<HashMap<(Span, Symbol), Span>>> = Init::default();
+	let mut declared = DECLARED.get().lock().unwrap();
+	match declared.get(&(scope, name)) {
+		Some(&prev) if prev != span => {
+			let err = err!(@span => "`{}` is already declared in this scope", name.as_str());
+			Err(err.with_label(prev, "first declared here"))
+		}
+		_ => {
+			declared.insert((scope, name), span);
+			Ok(())
+		}
+	}
+}
@@ -0,0 +1,432 @@
+use super::*;
+
+/// Modules for the program being parsed, either loaded with `import` or
+/// from the program input.
+///
+/// Each module is loaded once and inserted in the program before the first
+/// module importing it, so its top-level code runs first.
+#[derive(Default)]
+pub struct Modules {
+	state: Mutex<ModuleState>,
+}
+
+#[derive(Default)]
+struct ModuleState {
+	/// Source map from the session, used to load the imported modules.
+	sources: Option<Arc<SourceMap>>,
+	list: Vec<&'static Module>,
+	imports: Vec<(Source, Source, Span)>,
+	exports: Vec<(Source, Node)>,
+}
+
+/// Module in the program, with its source node.
+#[derive(Debug)]
+pub struct Module {
+	/// Name used to access the module, from the last component of its
+	/// import path.
+	pub name: Symbol,
+	/// Path used to import the module, or none for the program input.
+	pub path: Option<&'static str>,
+	pub src: Source,
+	pub node: Node,
+}
+
+impl Modules {
+	fn get() -> &'static Self {
+		static MODULES: Init<Modules> = Init::default();
+		MODULES.get()
+	}
+
+	/// Starts a new program with the input sources as modules, loading any
+	/// imported modules with the given source map.
+	pub fn reset<T: IntoIterator<Item = Node>>(input: T, sources: Option<Arc<SourceMap>>) {
+		let mut state = Self::get().state.lock().unwrap();
+		*state = ModuleState {
+			sources,
+			..Default::default()
+		};
+		for node in input {
+			if let Some(&src) = node.cast::<Source>() {
+				let name = src.path().and_then(|x| x.file_stem()).map(|x| x.to_string_lossy());
+				let name = Symbol::get(name.as_deref().unwrap_or(src.name()));
+				let path = None;
+				let module: &'static Module = Arena::get().store(Module { name, path, src, node });
+				state.list.push(module);
+			}
+		}
+	}
+
+	/// Returns the module for a source loaded with `import`, if any.
+	pub fn imported(src: Source) -> Option<&'static Module> {
+		let state = Self::get().state.lock().unwrap();
+		state.list.iter().copied().find(|x| x.src == src && x.path.is_some())
+	}
+
+	/// Returns the name for a C symbol declared in the source, prefixed by
+	/// the module path for imported modules.
+	pub fn mangle(name: &str, src: Source) -> String {
+		match Self::imported(src).and_then(|x| x.path) {
+			Some(path) => format!("{}${name}", escape_path(path)),
+			None => name.to_string(),
+		}
+	}
+}
+
+impl ModuleState {
+	fn find(&self, src: Source) -> Option<&'static Module> {
+		// sources loaded by other source maps are a different source
+		let same = |x: &&Module| x.src == src || (x.src.path().is_some() && x.src.path() == src.path());
+		self.list.iter().copied().find(same)
+	}
+
+	/// Returns the import chain from `from` to `to`, if any.
+	fn chain(&self, from: Source, to: Source, seen: &mut Vec<Source>) -> Option<Vec<(Source, Span)>> {
+		if from == to {
+			return Some(Vec::new());
+		}
+
+		if seen.contains(&from) {
+			return None;
+		}
+		seen.push(from);
+
+		for &(a, b, span) in self.imports.iter() {
+			if a == from {
+				if let Some(mut chain) = self.chain(b, to, seen) {
+					chain.insert(0, (b, span));
+					return Some(chain);
+				}
+			}
+		}
+		None
+	}
+}
+
+/// Import statement, which has no effect on its own.
+#[derive(Debug)]
+pub struct Import(pub &'static Module);
+
+impl IsValue for Import {
+	fn output_code(&self, _ctx: CodeContext, node: Node) -> Result<Code> {
+		Ok(Code {
+			expr: Expr::None,
+			span: node.span(),
+		})
+	}
+}
+
+/// Parses `import path/to/module` statements.
+///
+/// The path is relative to the importing source, without the `.bit`
+/// extension. The module name is bound from the end of the statement to
+/// the end of the source, giving access to its exported names with
+/// `module.name`.
+#[derive(Debug)]
+pub struct ParseImport;
+
+impl Eval for ParseImport {
+	fn precedence(&self) -> Precedence {
+		Precedence::Import
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			let (line, owner) = match top_level(*it, "import")? {
+				Some(parent) => parent,
+				None => continue,
+			};
+
+			let src = line.source();
+			let rest = Span::new(src, it.span().end(), line.span().end()).text();
+			let path = rest.trim();
+			let sta = it.span().end() + (rest.len() - rest.trim_start().len());
+			let path_span = Span::new(src, sta, sta + path.len());
+			if path.is_empty() {
+				raise!(@it => "expected a module path after `import`");
+			}
+
+			let name = match path.rsplit('/').next().filter(|x| !x.is_empty()) {
+				Some(name) => name,
+				None => raise!(@path_span => "invalid module path `{path}`"),
+			};
+
+			let dir = src.path().and_then(|x| x.parent()).unwrap_or(Path::new("."));
+			let file = dir.join(format!("{path}.bit"));
+			if !file.is_file() {
+				raise!(@path_span => "module `{path}` not found at `{}`", file.display());
+			}
+
+			let modules = Modules::get();
+			let sources = modules.state.lock().unwrap().sources.clone();
+			let imported = match sources {
+				Some(sources) => sources.load_file(&file)?,
+				None => raise!(@path_span => "cannot load module `{path}` without a source map for the session"),
+			};
+
+			// the program is changed with the state locked, since imports in
+			// other sources may run concurrently
+			let mut state = modules.state.lock().unwrap();
+			let found = state.find(imported);
+			let imported = found.map(|x| x.src).unwrap_or(imported);
+			if let Some(chain) = state.chain(imported, src, &mut Vec::new()) {
+				let mut names = vec![src.name(), imported.name()];
+				names.extend(chain.iter().map(|x| x.0.name()));
+				let mut err = err!(@path_span => "import cycle: {}", names.join(" -> "));
+				for (from, span) in chain {
+					err = err.with_label(span, format!("imports `{}`", from.name()));
+				}
+				Err(err)?;
+			}
+
+			let program = match owner.parent() {
+				Some(program) => program,
+				None => raise!(@it => "`import` is not in a program"),
+			};
+
+			let module = match found {
+				Some(module) => {
+					if module.node.parent() == Some(program) && module.node.index() > owner.index() {
+						module.node.remove();
+						program.insert_nodes(owner.index(), [module.node]);
+					}
+					module
+				}
+				None => {
+					let node = Node::new_at(imported, imported.span());
+					program.insert_nodes(owner.index(), [node]);
+
+					let name = Symbol::get(name);
+					let path = Some(Arena::get().str(path));
+					let module: &'static Module = Arena::get().store(Module {
+						name,
+						path,
+						src: imported,
+						node,
+					});
+					state.list.push(module);
+					module
+				}
+			};
+			state.imports.push((src, module.src, path_span));
+			drop(state);
+
+			for it in line.children() {
+				it.set_done(true);
+			}
+			let import = Build::new(Import(module)).at(line.span()).node();
+			line.remove_nodes(..);
+			line.push_node(import);
+
+			let span = Span::new(src, line.span().end(), src.len());
+			WORDS.get(module.name).set_span(span, BindModule(module));
+		}
+		Ok(())
+	}
+}
+
+/// Parses the `export` prefix for top-level `let`, `fn`, and `struct`
+/// declarations, making them accessible to modules importing the source.
+#[derive(Debug)]
+pub struct ParseExport;
+
+impl Eval for ParseExport {
+	fn precedence(&self) -> Precedence {
+		Precedence::Import
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			let (line, _) = match top_level(*it, "export")? {
+				Some(parent) => parent,
+				None => continue,
+			};
+
+			match it.next().and_then(|x| x.cast::<Token>()) {
+				Some(&Token::Word(name, ..)) if [symbols.LET, symbols.FN, symbols.STRUCT].contains(&name) => {}
+				_ => raise!(@it => "expected `let`, `fn`, or `struct` after `export`"),
+			}
+
+			it.set_done(true);
+			it.remove();
+
+			let mut state = Modules::get().state.lock().unwrap();
+			state.exports.push((line.source(), line));
+		}
+		Ok(())
+	}
+}
+
+/// Binds a module name, resolving `module.name` to the exported declaration.
+#[derive(Debug)]
+pub struct BindModule(pub &'static Module);
+
+impl Eval for BindModule {
+	fn precedence(&self) -> Precedence {
+		Precedence::VarBinding
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let module = self.0;
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			// the `.name` has already been parsed as a member by `ParseMember`
+			let (member, name) = match it.next().and_then(|x| x.cast::<Member>().map(|m| (x, m.0))) {
+				Some(found) => found,
+				None => raise!(@it => "expected `.` and a name after module `{}`", module.name.as_str()),
+			};
+
+			let (def, line) = match find_declaration(module.node, name) {
+				Some(found) => found,
+				None => raise!(@member => "module `{}` has no `{}`", module.name.as_str(), name.as_str()),
+			};
+
+			let exported = {
+				let state = Modules::get().state.lock().unwrap();
+				state.exports.iter().any(|x| x.1 == line)
+			};
+			if !exported {
+				let err = err!(@member => "`{}` is private to module `{}`", name.as_str(), module.name.as_str());
+				let err = err.with_label(line, "declared here");
+				Err(err.with_help("add `export` to the declaration to access it from other modules"))?;
+			}
+
+			it.set_done(true);
+			it.remove();
+
+			match def {
+				Definition::Var(var, decl) => BindVar(var, decl).execute(&[member])?,
+				Definition::Fn(func) => BindFn(func).execute(&[member])?,
+				Definition::Struct(typ) => BindStruct(typ).execute(&[member])?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Returns the line and the parent node for a statement keyword, checking
+/// that it is at the top level of its source. Returns none if the node has
+/// no parent.
+fn top_level(node: Node, keyword: &str) -> Result<Option<(Node, Node)>> {
+	let line = match node.parent() {
+		Some(line) => line,
+		None => return Ok(None),
+	};
+
+	if node.index() != 0 {
+		raise!(@node => "`{keyword}` must be at the start of a line");
+	}
+
+	match line.parent() {
+		Some(owner) if owner.cast::<Source>().is_some() => Ok(Some((line, owner))),
+		_ => raise!(@node => "`{keyword}` must be at the top level of a module"),
+	}
+}
+
+/// Finds a top-level declaration in the module node, returning it with its
+/// line.
+fn find_declaration(module: Node, name: Symbol) -> Option<(Definition, Node)> {
+	for line in module.children() {
+		for it in line.children() {
+			if let Some(decl) = it.cast::<Let>().filter(|x| x.0.name() == name) {
+				return Some((Definition::Var(decl.0, it), line));
+			} else if let Some(decl) = it.cast::<FnDecl>().filter(|x| x.0.name() == name) {
+				return Some((Definition::Fn(decl.0), line));
+			} else if let Some(decl) = it.cast::<StructDecl>().filter(|x| x.0.name() == name) {
+				return Some((Definition::Struct(decl.0), line));
+			}
+		}
+	}
+	None
+}
+
+/// Escapes a module path for a C symbol, so that different paths give
+/// different names. Characters not valid in a C name are replaced by their
+/// hex code between underscores, and `_` itself is doubled.
+fn escape_path(path: &str) -> String {
+	let mut output = String::new();
+	for chr in path.chars() {
+		if chr == '_' {
+			output.push_str("__");
+		} else if is_ident(chr, true) {
+			output.push(chr);
+		} else {
+			output.push_str(&format!("_{:x}_", chr as u32));
+		}
+	}
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn run(files: &[(&str, &str)]) -> Result<Value> {
+		let dir = temp::dir()?;
+		for (name, text) in files {
+			dir.file(name)?.write(text)?;
+		}
+
+		let sources = Arc::new(SourceMap::new(dir.path())?);
+		let src = sources.load_file(files[0].0)?;
+		let mut session = Session::new();
+		session.set_sources(sources);
+		session.execute(&[src])
+	}
+
+	#[test]
+	fn import_once() -> Result<()> {
+		let _lock = init_test();
+
+		let main = "import a\nimport b\na.get() + b.get()";
+		let a = "import c\nexport fn get():\n\treturn c.next()";
+		let b = "import c\nexport fn get():\n\treturn c.next() * 10";
+		let c = "export fn next():\n\treturn 2";
+		let value = run(&[("main.bit", main), ("a.bit", a), ("b.bit", b), ("c.bit", c)])?;
+		assert_eq!(value.cast::<i64>(), Some(&22));
+		Ok(())
+	}
+
+	#[test]
+	fn exported_structs() -> Result<()> {
+		let _lock = init_test();
+
+		let main = "import m\nlet p = m.P(20, 22)\np.x + p.y";
+		let m = "export struct P: x: i64, y: i64";
+		let value = run(&[("main.bit", main), ("m.bit", m)])?;
+		assert_eq!(value.cast::<i64>(), Some(&42));
+
+		let err = run(&[("main.bit", "import m\nm.P(1)"), ("m.bit", "struct P: x: i64")]).unwrap_err();
+		assert!(err.to_string().contains("`P` is private to module `m`"));
+		Ok(())
+	}
+
+	#[test]
+	fn escaped_paths() {
+		assert_eq!(escape_path("a/b"), "a_2f_b");
+		assert_eq!(escape_path("a_b"), "a__b");
+		assert_eq!(escape_path("../x.y"), "_2e__2e__2f_x_2e_y");
+	}
+
+	#[test]
+	fn private_names_and_cycles() -> Result<()> {
+		let _lock = init_test();
+
+		let err = run(&[("main.bit", "import m\nm.x"), ("m.bit", "let x = 1")]).unwrap_err();
+		assert!(err.to_string().contains("`x` is private to module `m`"));
+
+		let err = run(&[("main.bit", "import m\nm.y"), ("m.bit", "export let x = 1")]).unwrap_err();
+		assert!(err.to_string().contains("module `m` has no `y`"));
+
+		let files = [("main.bit", "import a"), ("a.bit", "import b"), ("b.bit", "import a")];
+		let err = run(&files).unwrap_err();
+		let err = err.to_string();
+		let cycle = err.lines().next().unwrap().split(" -> ").collect::<Vec<_>>();
+		assert!(cycle[0].starts_with("error: import cycle: "));
+		assert!(cycle[0].ends_with("b.bit") && cycle[1].ends_with("a.bit") && cycle[2].ends_with("b.bit"));
+		Ok(())
+	}
+}
@@ -0,0 +1,368 @@
+use super::*;
+
+/// Operators declared in the source, with their operator node as the value.
+pub static OPERATORS: Bindings = Bindings::new();
+
+/// Operators from previous programs in a session bound to each source.
+static PREVIOUS: Init<Mutex<HashMap<Source, Vec<&'static OperatorDef>>>> = Init::default();
+
+/// Operator declared with `operator infixl 6 "<>" = concat`, applying the
+/// function to its operands.
+#[derive(Debug)]
+pub struct OperatorDef {
+	pub symbol: Symbol,
+	pub assoc: Assoc,
+	pub level: u8,
+	pub func: Symbol,
+	pub span: Span,
+}
+
+/// Associativity for a declared operator.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Assoc {
+	Left,
+	Right,
+	/// The operator cannot be chained without parenthesis.
+	None,
+}
+
+impl Assoc {
+	pub fn parse(name: Symbol) -> Option<Assoc> {
+		let assoc = match name.as_str() {
+			"infixl" => Assoc::Left,
+			"infixr" => Assoc::Right,
+			"infix" => Assoc::None,
+			_ => return None,
+		};
+		Some(assoc)
+	}
+}
+
+/// Operator declaration, which has no effect on its own.
+#[derive(Debug)]
+pub struct OperatorDecl(pub &'static OperatorDef);
+
+impl IsValue for OperatorDecl {
+	fn output_code(&self, _ctx: CodeContext, node: Node) -> Result<Code> {
+		Ok(Code {
+			expr: Expr::None,
+			span: node.span(),
+		})
+	}
+}
+
+/// Operator symbol bound to its declaration, waiting to be parsed at the
+/// operator level.
+#[derive(Debug)]
+pub struct OperatorRef(pub &'static OperatorDef);
+
+impl IsValue for OperatorRef {
+	fn bind(&self, node: Node) {
+		OPERATORS.add(node)
+	}
+}
+
+/// Application of a declared operator, with the function name as the first
+/// child followed by the operands. The function binding replaces this with
+/// a [`Call`].
+#[derive(Debug)]
+pub struct ApplyOperator(pub &'static OperatorDef);
+
+impl IsValue for ApplyOperator {
+	fn output_code(&self, _ctx: CodeContext, node: Node) -> Result<Code> {
+		let op = self.0;
+		let err = err!(@node => "operator `{}` requires `{}` to be a function", op.symbol.as_str(), op.func.as_str());
+		Err(err.with_label(op.span, "operator declared here"))
+	}
+}
+
+/// Parses `operator infixl 6 "<>" = concat` declarations.
+///
+/// The operator is bound from the end of the declaration until the end of
+/// the source. The symbol itself is added to the source lexer before this
+/// (see [`declared_symbols`]).
+#[derive(Debug)]
+pub struct ParseOperatorDecl;
+
+impl Eval for ParseOperatorDecl {
+	fn precedence(&self) -> Precedence {
+		Precedence::OperatorDecl
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			let line = match it.parent() {
+				Some(line) => line,
+				None => continue,
+			};
+
+			if it.index() != 0 {
+				raise!(@it => "`operator` must be at the start of a line");
+			}
+
+			let usage = "expected `operator infixl|infixr|infix LEVEL \"SYMBOL\" = FUNCTION`";
+			let args = line.children();
+			let token = |n: usize| args.get(n).and_then(|x| x.cast::<Token>());
+			let (assoc, level, text, func) = match (token(1), token(2), token(3), token(4), token(5)) {
+				(
+					Some(&Token::Word(assoc, ..)),
+					Some(Token::Integer(level)),
+					Some(Token::Literal(text)),
+					Some(&Token::Symbol(eq, ..)),
+					Some(&Token::Word(func, ..)),
+				) if args.len() == 6 && eq == symbols.ASSIGN => (assoc, level, text, func),
+				_ => raise!(@line => "invalid operator declaration, {usage}"),
+			};
+
+			let assoc = match Assoc::parse(assoc) {
+				Some(assoc) => assoc,
+				None => raise!(@args[1] => "invalid associativity `{}`, {usage}", assoc.as_str()),
+			};
+
+			let level = match level.text().parse::<u8>() {
+				Ok(level) if level <= 9 => level,
+				_ => raise!(@level => "invalid operator level `{}`, expected 0 to 9", level.text()),
+			};
+
+			// operators are symbols, since a word is used for the function
+			let text = parse_str(text)?;
+			if !is_operator(text) {
+				raise!(@args[3] => "invalid operator `{text}`, expected a symbol");
+			}
+
+			let symbol = Symbol::get(text);
+			if Op::binary(symbol).is_some() || Op::unary(symbol).is_some() {
+				raise!(@args[3] => "cannot redeclare the built-in operator `{text}`");
+			}
+
+			for it in args {
+				it.set_done(true);
+			}
+
+			let def = Arena::get().store(OperatorDef {
+				symbol,
+				assoc,
+				level,
+				func,
+				span: line.span(),
+			});
+
+			let decl = Build::new(OperatorDecl(def)).at(line.span()).node();
+			line.remove_nodes(..);
+			line.push_node(decl);
+
+			let src = line.source();
+			let span = Span::new(src, line.span().end(), src.len());
+			SYMBOLS.get(symbol).set_span(span, BindOperator(def));
+		}
+		Ok(())
+	}
+}
+
+/// Binds a declared operator symbol to its declaration.
+#[derive(Debug)]
+pub struct BindOperator(pub &'static OperatorDef);
+
+impl Eval for BindOperator {
+	fn precedence(&self) -> Precedence {
+		Precedence::OperatorDecl
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			it.set_done(true);
+			let node = Node::new_at(OperatorRef(self.0), it.span());
+			it.replace([node]);
+		}
+		Ok(())
+	}
+}
+
+/// Splits the parent node at every declared operator with the level and
+/// groups the operands according to the operator associativity.
+#[derive(Debug)]
+pub struct ParseOperator(pub u8);
+
+impl ParseOperator {
+	fn get_op(&self, node: Node) -> Option<&'static OperatorDef> {
+		let op = node.cast::<OperatorRef>()?.0;
+		if op.level == self.0 {
+			Some(op)
+		} else {
+			None
+		}
+	}
+}
+
+impl Eval for ParseOperator {
+	fn precedence(&self) -> Precedence {
+		Precedence::Operator(self.0)
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			if it.done() || self.get_op(*it).is_none() {
+				continue;
+			}
+
+			let parent = match it.parent() {
+				Some(parent) => parent,
+				None => continue,
+			};
+
+			let ops = parent.children().into_iter();
+			let ops = ops.filter_map(|node| self.get_op(node).map(|op| (node, op)));
+			let ops = ops.collect::<Vec<_>>();
+
+			let (first, first_op) = ops[0];
+			for &(node, op) in ops.iter().skip(1) {
+				if op.assoc != first_op.assoc {
+					let err = err!(@node => "cannot mix `{}` and `{}` without parenthesis", first_op.symbol.as_str(), op.symbol.as_str());
+					let err = err.with_label(first, "both have the same level, but a different associativity");
+					Err(err)?;
+				} else if op.assoc == Assoc::None {
+					let err = err!(@node => "operator `{}` cannot be chained without parenthesis", op.symbol.as_str());
+					Err(err.with_label(op.span, "declared here without associativity"))?;
+				}
+			}
+
+			let children = parent.remove_nodes(..);
+			let mut operands = Vec::new();
+			let mut sta = 0;
+			for &(node, op) in ops.iter() {
+				node.set_done(true);
+				let end = node.index();
+				if sta >= end {
+					let side = if sta == 0 { "left" } else { "right" };
+					raise!(@node => "missing {side} operand for `{}`", op.symbol.as_str());
+				}
+				operands.push(new_operand(children.range(sta..end)));
+				sta = end + 1;
+			}
+
+			let (last, last_op) = ops[ops.len() - 1];
+			if sta >= children.len() {
+				raise!(@last => "missing right operand for `{}`", last_op.symbol.as_str());
+			}
+			operands.push(new_operand(children.range(sta..)));
+
+			let apply = |(node, op): (Node, &'static OperatorDef), lhs: Node, rhs: Node| {
+				let func = Node::new(Token::Word(op.func, node.span()));
+				let span = lhs.span().merged(rhs.span());
+				Build::new(ApplyOperator(op)).at(span).children([func, lhs, rhs]).node()
+			};
+
+			let node = if first_op.assoc == Assoc::Right {
+				let mut operands = operands.into_iter().rev();
+				let mut rhs = operands.next().unwrap();
+				for (op, lhs) in ops.into_iter().rev().zip(operands) {
+					rhs = apply(op, lhs, rhs);
+				}
+				rhs
+			} else {
+				let mut operands = operands.into_iter();
+				let mut lhs = operands.next().unwrap();
+				for (op, rhs) in ops.into_iter().zip(operands) {
+					lhs = apply(op, lhs, rhs);
+				}
+				lhs
+			};
+			parent.push_node(node);
+		}
+		Ok(())
+	}
+}
+
+/// Binds an operator declared by a previous program in a session for the
+/// whole source, including its symbol in the source lexer.
+pub fn bind_previous_operator(src: Source, op: &'static OperatorDef) {
+	PREVIOUS.get().lock().unwrap().entry(src).or_default().push(op);
+	SYMBOLS.get(op.symbol).set_span(src.span(), BindOperator(op));
+}
+
+/// Returns the symbols for operators declared in the tokens, so they can be
+/// added to the lexer before parsing the source. This includes operators
+/// bound from previous programs with [`bind_previous_operator`].
+pub fn declared_symbols(src: Source, tokens: &[Token]) -> Vec<&'static str> {
+	let symbols = Symbols::get();
+	let mut output = Vec::new();
+	if let Some(previous) = PREVIOUS.get().lock().unwrap().get(&src) {
+		output.extend(previous.iter().map(|x| x.symbol.as_str()));
+	}
+
+	let lines = tokens.split(|x| matches!(x, Token::Break(..)));
+	for line in lines {
+		if let [Token::Word(keyword, ..), Token::Word(..), Token::Integer(..), Token::Literal(span), ..] = line {
+			if *keyword != symbols.OPERATOR {
+				continue;
+			}
+
+			if let Ok(text) = parse_str(span) {
+				if is_operator(text) {
+					output.push(text);
+				}
+			}
+		}
+	}
+	output
+}
+
+/// Returns true if the text is valid as a declared operator symbol.
+fn is_operator(text: &str) -> bool {
+	let invalid = |chr: char| is_ident(chr, true) || is_space(chr) || "\r\n#'\"".contains(chr);
+	!text.is_empty() && !text.chars().any(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn operator_levels() {
+		use Precedence::*;
+		let order = [
+			SessionBinding,
+			Operator(0),
+			Operator(1),
+			Builtin,
+			OpOr,
+			Operator(2),
+			OpAnd,
+			Operator(3),
+			OpNot,
+			OpCompare,
+			Operator(4),
+			OpIn,
+			OpRange,
+			Operator(5),
+			OpAdd,
+			Operator(6),
+			OpMul,
+			Operator(7),
+			Operator(8),
+			Operator(9),
+			OpUnary,
+		];
+		assert!(order.windows(2).all(|x| x[0] < x[1]), "{order:?}");
+		assert!(Operator(9) < Last);
+	}
+
+	#[test]
+	fn operator_levels_in_expressions() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let run = |name: &str, level: u8| {
+			let text = format!("fn f(a, b):\n\treturn a * 10 + b\noperator infixl {level} \"<>\" = f\n1 + 2 <> 3 * 4");
+			let value = Session::new().execute(&[sources.from_string(name, &text)])?;
+			Ok::<_, Error>(*value.cast::<i64>().unwrap())
+		};
+
+		// (1 + 2) <> (3 * 4), 1 + (2 <> 3 * 4), and 1 + (2 <> 3) * 4
+		assert_eq!(run("level-5", 5)?, 42);
+		assert_eq!(run("level-6", 6)?, 33);
+		assert_eq!(run("level-7", 7)?, 93);
+		Ok(())
+	}
+}
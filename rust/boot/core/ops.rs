@@ -0,0 +1,178 @@
+use super::*;
+
+#[derive(Debug)]
+pub struct BinaryOp(pub Operator);
+
+impl IsValue for BinaryOp {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 2 {
+			raise!(@node => "invalid binary `{}` with {} operands", self.0.op, children.len());
+		}
+
+		let lhs = children[0].compile(ctx)?;
+		let rhs = children[1].compile(ctx)?;
+		let store = Arena::get();
+		Ok(Code {
+			expr: Expr::Binary(self.0, store.store(lhs), store.store(rhs)),
+			span: node.span(),
+		})
+	}
+}
+
+#[derive(Debug)]
+pub struct UnaryOp(pub Operator);
+
+impl IsValue for UnaryOp {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 1 {
+			raise!(@node => "invalid unary `{}` with {} operands", self.0.op, children.len());
+		}
+
+		let arg = children[0].compile(ctx)?;
+		Ok(Code {
+			expr: Expr::Unary(self.0, Arena::get().store(arg)),
+			span: node.span(),
+		})
+	}
+}
+
+/// Splits the parent node at every binary operator of the same precedence
+/// and groups the operands from left to right.
+#[derive(Debug)]
+pub struct ParseBinaryOp(pub Precedence);
+
+impl ParseBinaryOp {
+	fn get_op(&self, node: Node) -> Option<Operator> {
+		let token = node.cast::<Token>()?;
+		let op = Op::binary(token.symbol())?;
+		if op.precedence() != self.0 || is_prefix(node) {
+			return None;
+		}
+
+		Some(Operator { op, span: node.span() })
+	}
+}
+
+impl Eval for ParseBinaryOp {
+	fn precedence(&self) -> Precedence {
+		self.0
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let parent = if let Some(parent) = it.parent() {
+				parent
+			} else {
+				continue;
+			};
+
+			let ops = parent
+				.children()
+				.into_iter()
+				.filter_map(|node| self.get_op(node).map(|op| (node, op)))
+				.collect::<Vec<_>>();
+			if ops.is_empty() {
+				continue;
+			}
+
+			let children = parent.remove_nodes(..);
+			let operand = |sta: usize, end: usize, op: Operator| -> Result<Node> {
+				if sta >= end {
+					let side = if sta == 0 { "left" } else { "right" };
+					raise!(@op.span => "missing {side} operand for `{}`", op.op);
+				}
+				Ok(new_operand(children.range(sta..end)))
+			};
+
+			let (first, first_op) = ops[0];
+			let mut cur = first.index() + 1;
+			let mut lhs = operand(0, first.index(), first_op)?;
+			for (n, &(node, op)) in ops.iter().enumerate() {
+				node.set_done(true);
+				let end = ops.get(n + 1).map(|x| x.0.index()).unwrap_or(children.len());
+				let rhs = operand(cur, end, op)?;
+				cur = end + 1;
+
+				let span = lhs.span().merged(rhs.span());
+				let node = Node::new_at(BinaryOp(op), span);
+				node.set_done(true);
+				node.append_nodes([lhs, rhs]);
+				lhs = node;
+			}
+
+			parent.push_node(lhs);
+		}
+		Ok(())
+	}
+}
+
+/// Applies a prefix operator to the rest of the parent node.
+#[derive(Debug)]
+pub struct ParseUnaryOp(pub Precedence);
+
+impl Eval for ParseUnaryOp {
+	fn precedence(&self) -> Precedence {
+		self.0
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let parent = if let Some(parent) = it.parent() {
+				parent
+			} else {
+				continue;
+			};
+
+			let op = match it.cast::<Token>().and_then(|x| Op::unary(x.symbol())) {
+				Some(op) if op.precedence() == self.0 => op,
+				_ => continue,
+			};
+
+			if it.index() != 0 {
+				raise!(@it => "unexpected `{op}` operator");
+			}
+
+			if parent.len() == 1 {
+				raise!(@it => "missing operand for `{op}`");
+			}
+
+			it.set_done(true);
+			let children = parent.remove_nodes(..);
+			let node = Node::new_at(UnaryOp(Operator { op, span: it.span() }), children.span());
+			node.set_done(true);
+			node.push_node(new_operand(children.range(1..)));
+			parent.push_node(node);
+		}
+		Ok(())
+	}
+}
+
+/// Returns true if the operator node is in a prefix position, that is, at
+/// the start of its parent or following another symbol.
+fn is_prefix(node: Node) -> bool {
+	match node.prev() {
+		None => true,
+		Some(prev) => matches!(prev.cast::<Token>(), Some(Token::Symbol(..))),
+	}
+}
+
+pub(crate) fn new_operand(nodes: NodeList) -> Node {
+	if nodes.len() == 1 {
+		nodes[0]
+	} else {
+		let group = Node::new_at(Group, nodes.span());
+		group.set_done(true);
+		group.append_nodes(nodes);
+		group
+	}
+}
@@ -5,7 +5,17 @@ pub struct Print;
 
 impl IsValue for Print {
 	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
-		let list = Code::list(ctx, node.children())?;
+		// values separated only by spaces, as in `print msg answer`, are
+		// still printed as separate arguments
+		let mut args = Vec::new();
+		for it in node.children() {
+			match it.cast::<Group>() {
+				Some(..) if it.len() > 1 => args.extend(it.children()),
+				_ => args.push(it),
+			}
+		}
+
+		let list = Code::list(ctx, args)?;
 		Ok(Code {
 			expr: Expr::Print(list),
 			span: node.span(),
@@ -13,6 +23,10 @@ impl IsValue for Print {
 	}
 }
 
+/// Parses `print` statements, with arguments separated by commas.
+///
+/// Arguments can also be separated by spaces, which was the only form
+/// before commas were supported.
 #[derive(Debug)]
 pub struct ParsePrint;
 
@@ -22,19 +36,75 @@ impl Eval for ParsePrint {
 	}
 
 	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		let pattern = Pattern::new([Match::Word(symbols.PRINT), Match::Rest]);
+		let comma = Match::Symbol(symbols.COMMA);
 		for it in nodes {
-			it.set_done(true);
-			if let Some(parent) = it.parent() {
-				let index = it.index();
-				let children = parent.remove_nodes(index..);
-				let print = Node::new_at(Print, children.span());
-				print.set_done(true);
+			let found = match pattern.match_at(*it) {
+				Some(found) => found,
+				None => continue,
+			};
+
+			let args = found.get(1);
+			let parts = args.split(comma);
+			if let Some(empty) = parts.iter().find(|x| x.len() == 0) {
+				raise!(@empty.span_or_pos() => "expected an expression");
+			}
 
-				let children = children.range(1..);
-				print.append_nodes(children);
+			found.set_done();
+			for it in args.into_iter().filter(|x| comma.matches(*x)) {
+				it.set_done(true);
+			}
 
-				parent.insert_nodes(index, [print]);
+			let mut print = Build::new(Print).at(found.span());
+			found.remove();
+			for arg in parts {
+				print = print.group(arg);
 			}
+			found.insert([print.node()]);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Returns the number of arguments for each `print` in the program.
+	fn print_args(name: &str, text: &str) -> Result<Vec<usize>> {
+		let src = SourceMap::new(".")?.from_string(name, text);
+		let mut session = Session::new();
+		let program = session.load(&[src]);
+		session.solve(program)?;
+		let code = session.compile(program)?;
+		let Expr::Sequence(list) = code.expr else {
+			panic!("expected a sequence, got {code:?}");
+		};
+		let count = |x: &Code| match x.expr {
+			Expr::Print(args) => args.len(),
+			_ => panic!("expected a print, got {x:?}"),
+		};
+		Ok(list.iter().map(count).collect())
+	}
+
+	#[test]
+	fn commas_and_spaces() -> Result<()> {
+		let _lock = init_test();
+
+		let text = "print 1, 2\nprint 1 2\nprint 1 2, 3\nprint 1, 2 3\nprint (1 + 2) 3, 4\nprint";
+		assert_eq!(print_args("mixed", text)?, [2, 2, 3, 3, 3, 0]);
+		Ok(())
+	}
+
+	#[test]
+	fn empty_arguments() -> Result<()> {
+		let _lock = init_test();
+
+		for (text, pos) in [("print 1,,2", 8), ("print , 1", 6), ("print 1,", 8)] {
+			let err = print_args("empty", text).unwrap_err();
+			assert!(err.to_string().contains("expected an expression"), "{err}");
+			assert_eq!(err.span().map(|x| x.sta()), Some(pos), "{text}");
 		}
 		Ok(())
 	}
@@ -28,6 +28,15 @@ impl IsValue for Program {
 	}
 
 	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
-		Code::sequence(ctx, node.children())
+		// spans from different sources cannot be merged, so a program with
+		// multiple sources has no span
+		let code = Code::list(ctx, node.children())?;
+		match code {
+			[code] => Ok(*code),
+			_ => Ok(Code {
+				expr: Expr::Sequence(code),
+				span: Span::empty(),
+			}),
+		}
 	}
 }
@@ -90,6 +90,9 @@ impl Eval for ExpandRaw {
 						let children = tokens.list().iter().map(|x| Node::new(*x));
 						group.set_done(true);
 						group.append_nodes(children);
+
+						// keep nested nodes (e.g. indented blocks) after the tokens
+						group.append_nodes(it.remove_nodes(..));
 						it.replace([group]);
 					}
 					Raw::Empty(..) => {
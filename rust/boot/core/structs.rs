@@ -0,0 +1,404 @@
+use super::*;
+
+/// Struct declaration with a [`FieldDecl`] child for each field.
+#[derive(Debug)]
+pub struct StructDecl(pub Struct);
+
+impl IsValue for StructDecl {
+	fn output_code(&self, _ctx: CodeContext, node: Node) -> Result<Code> {
+		let mut fields = Vec::new();
+		for it in node.children() {
+			let name = match it.cast::<FieldDecl>() {
+				Some(decl) => decl.0,
+				None => raise!(@it => "invalid field in struct declaration"),
+			};
+
+			let typ = match it.children().as_slice() {
+				[typ] => TypeName::resolve(*typ)?,
+				_ => raise!(@it => "invalid type for field `{}`", name.as_str()),
+			};
+
+			fields.push(Field {
+				name,
+				span: it.span(),
+				typ,
+			});
+		}
+		self.0.set_fields(fields)?;
+
+		// the declaration itself has no effect, like a function declaration
+		Ok(Code {
+			expr: Expr::None,
+			span: node.span(),
+		})
+	}
+}
+
+/// Field in a struct declaration with its type as the single child.
+#[derive(Debug)]
+pub struct FieldDecl(pub Symbol);
+
+impl IsValue for FieldDecl {}
+
+/// Type in a field declaration. A list type has its item type as the single
+/// child.
+#[derive(Debug)]
+pub enum TypeName {
+	Builtin(Type),
+	List,
+	Struct(Struct),
+}
+
+impl IsValue for TypeName {}
+
+impl TypeName {
+	pub fn resolve(node: Node) -> Result<Type> {
+		let typ = match node.cast::<TypeName>() {
+			Some(&TypeName::Builtin(typ)) => typ,
+			Some(&TypeName::Struct(typ)) => Type::Struct(typ),
+			Some(TypeName::List) => match node.first() {
+				Some(item) => Type::list(Self::resolve(item)?),
+				None => raise!(@node => "missing item type for list"),
+			},
+			None => raise!(@node => "expected a type"),
+		};
+		Ok(typ)
+	}
+}
+
+/// Struct constructor with one child for each field value.
+#[derive(Debug)]
+pub struct New(pub Struct);
+
+impl IsValue for New {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let args = Code::list(ctx, node.children())?;
+		Ok(Code {
+			expr: Expr::New(self.0, args),
+			span: node.span(),
+		})
+	}
+}
+
+/// Field access with the struct value as the single child.
+#[derive(Debug)]
+pub struct FieldAccess(pub Symbol);
+
+impl IsValue for FieldAccess {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 1 {
+			raise!(@node => "invalid field access with {} children", children.len());
+		}
+
+		let value = children[0].compile(ctx)?;
+		Ok(Code {
+			expr: Expr::Field(Arena::get().store(value), self.0),
+			span: node.span(),
+		})
+	}
+}
+
+/// A `.name` following a value, parsed as a [`FieldAccess`] by [`ParseField`].
+#[derive(Debug)]
+pub struct Member(pub Symbol);
+
+pub static MEMBERS: Bindings = Bindings::new();
+
+impl IsValue for Member {
+	fn bind(&self, node: Node) {
+		MEMBERS.add(node);
+	}
+}
+
+/// Parses `struct Name: field: type, ...` declarations.
+///
+/// As with functions, the struct name is bound to the entire enclosing
+/// scope, so field types can refer to structs declared later.
+#[derive(Debug)]
+pub struct ParseStruct;
+
+impl Eval for ParseStruct {
+	fn precedence(&self) -> Precedence {
+		Precedence::BlockParse
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		let comma = Match::Symbol(symbols.COMMA);
+		let colon = Match::Symbol(symbols.COLON);
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let line = if let Some(line) = it.parent() {
+				line
+			} else {
+				continue;
+			};
+
+			if it.index() != 0 {
+				raise!(@it => "`struct` must be at the start of a line");
+			}
+
+			let (name, name_node) = match it.next() {
+				Some(node) => match node.cast::<Token>() {
+					Some(&Token::Word(name, ..)) => (name, node),
+					_ => raise!(@it => "expected a struct name after `struct`"),
+				},
+				None => raise!(@it => "expected a struct name after `struct`"),
+			};
+
+			let nodes = line.remove_nodes(..);
+			if !nodes.get(2).is_some_and(|x| colon.matches(x)) {
+				raise!(@nodes.range(..2).span() => "expected `:` and a list of fields after the struct name");
+			}
+
+			for it in nodes.range(..3) {
+				it.set_done(true);
+			}
+
+			let list = nodes.range(3..);
+			for it in list.into_iter().filter(|x| comma.matches(*x)) {
+				it.set_done(true);
+			}
+
+			let scope = if let Some(scope) = line.scope() {
+				scope.span()
+			} else {
+				line.span()
+			};
+			declare_in_scope(scope, name, name_node.span())?;
+
+			let typ = Struct::new(name, name_node.span());
+			let mut decl = Build::new(StructDecl(typ)).at(nodes.span());
+			let mut names: Vec<(Symbol, Span)> = Vec::new();
+			for (n, field) in list.split(comma).into_iter().enumerate() {
+				let field_name = match (field.get(0).and_then(|x| x.cast::<Token>()), field.get(1)) {
+					(Some(&Token::Word(name, ..)), Some(sep)) if colon.matches(sep) => name,
+					_ => raise!(@field.span_or_pos() => "expected `name: type` for field #{}", n + 1),
+				};
+
+				if field.len() == 2 {
+					raise!(@field.span() => "missing type for field `{}`", field_name.as_str());
+				}
+
+				if let Some((_, prev)) = names.iter().find(|x| x.0 == field_name) {
+					let err = err!(@field.get(0).unwrap() => "duplicate field `{}`", field_name.as_str());
+					Err(err.with_label(*prev, "first declared here"))?;
+				}
+				names.push((field_name, field.range(..1).span()));
+
+				for it in field.range(..2) {
+					it.set_done(true);
+				}
+
+				let field_type = parse_type(field.range(2..))?;
+				let node = Build::new(FieldDecl(field_name)).at(field.span()).child(field_type);
+				decl = decl.child(node.node());
+			}
+
+			if names.is_empty() {
+				raise!(@nodes.span() => "struct `{}` must have at least one field", name.as_str());
+			}
+
+			line.push_node(decl.node());
+			WORDS.get(name).set_span(scope, BindStruct(typ));
+		}
+		Ok(())
+	}
+}
+
+/// Parses the type for a field declaration.
+///
+/// Builtin types are resolved immediately, while struct names are left for
+/// [`BindStruct`].
+fn parse_type(nodes: NodeList) -> Result<Node> {
+	let node = match nodes.as_slice() {
+		[node] => *node,
+		_ => raise!(@nodes.span() => "expected a type"),
+	};
+
+	if let Some(&Token::Word(name, ..)) = node.cast::<Token>() {
+		return match Type::builtin(name.as_str()) {
+			Some(typ) => {
+				node.set_done(true);
+				Ok(Build::new(TypeName::Builtin(typ)).at(node.span()).node())
+			}
+			None => Ok(node),
+		};
+	}
+
+	if node.cast::<Bracket>().is_some() {
+		node.set_done(true);
+		let item = node.remove_nodes(..);
+		if item.len() == 0 {
+			raise!(@node => "missing item type for list");
+		}
+
+		let item = parse_type(item)?;
+		return Ok(Build::new(TypeName::List).at(node.span()).child(item).node());
+	}
+
+	raise!(@node => "expected a type")
+}
+
+/// Binds struct names to their declaration, parsing constructors such as
+/// `Point(1, 2)` and struct names used as field types.
+#[derive(Debug)]
+pub struct BindStruct(pub Struct);
+
+impl Eval for BindStruct {
+	fn precedence(&self) -> Precedence {
+		Precedence::VarBinding
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let comma = Match::symbol(",");
+		let typ = self.0;
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let parent = if let Some(parent) = it.parent() {
+				parent
+			} else {
+				continue;
+			};
+
+			if parent.cast::<FieldDecl>().is_some() || parent.cast::<TypeName>().is_some() {
+				it.set_done(true);
+				let node = Build::new(TypeName::Struct(typ)).at(it.span());
+				it.replace([node.node()]);
+				continue;
+			}
+
+			let args = match it.next() {
+				Some(args) if args.cast::<Paren>().is_some() => args,
+				_ => raise!(@it => "struct {typ} must be constructed with a list of field values"),
+			};
+
+			it.set_done(true);
+
+			let items = args.remove_nodes(..);
+			let values = items.split(comma);
+			for it in items.into_iter().filter(|x| comma.matches(*x)) {
+				it.set_done(true);
+			}
+
+			for (n, value) in values.iter().enumerate() {
+				if value.len() == 0 {
+					raise!(@value.span_or_pos() => "missing value #{} for struct {typ}", n + 1);
+				}
+			}
+
+			args.remove();
+
+			let node = Build::new(New(typ)).at(it.span().merged(args.span()));
+			let node = values.into_iter().fold(node, |node, value| node.group(value));
+			it.replace([node.node()]);
+		}
+		Ok(())
+	}
+}
+
+/// Parses a `.` followed by a name into a [`Member`].
+///
+/// This runs before names are bound, so the field name in `p.x` is never
+/// bound to a variable `x`.
+#[derive(Debug)]
+pub struct ParseMember;
+
+impl Eval for ParseMember {
+	fn precedence(&self) -> Precedence {
+		Precedence::Member
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		let pattern = Pattern::new([Match::Symbol(symbols.DOT), Match::AnyWord]);
+		for it in nodes {
+			if it.done() || it.parent().is_none() {
+				continue;
+			}
+
+			let found = match pattern.match_at(*it) {
+				Some(found) => found,
+				None => raise!(@it => "expected a field name after `.`"),
+			};
+
+			let name = found.node(1).cast::<Token>().unwrap().symbol();
+			found.set_done();
+			found.remove();
+
+			// the member is solved once it is parsed as a field access
+			let member = Node::new_at(Member(name), found.span());
+			found.insert([member]);
+		}
+		Ok(())
+	}
+}
+
+/// Parses a [`Member`] following a value as a field access, such as `p.x`.
+#[derive(Debug)]
+pub struct ParseField;
+
+impl Eval for ParseField {
+	fn precedence(&self) -> Precedence {
+		Precedence::Index
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			parse_member(*it)?;
+		}
+		Ok(())
+	}
+}
+
+pub(crate) fn parse_member(node: Node) -> Result<()> {
+	if node.done() || node.parent().is_none() {
+		return Ok(());
+	}
+
+	let name = node.cast::<Member>().unwrap().0;
+	let value = match postfix_operand(node)? {
+		Some(value) => value,
+		// an unbound name before the member is reported as unsolved
+		None if node
+			.prev()
+			.is_some_and(|x| matches!(x.cast::<Token>(), Some(Token::Word(..)))) =>
+		{
+			return Ok(());
+		}
+		None => raise!(@node => "missing value before `.{}`", name.as_str()),
+	};
+
+	node.set_done(true);
+
+	let pattern = Pattern::new([Match::Any, Match::value::<Member>()]);
+	let found = pattern.match_at(value).unwrap();
+	found.remove();
+	let access = Build::new(FieldAccess(name)).at(found.span()).child(value);
+	found.insert([access.node()]);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn duplicate_structs() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("dup", "struct P: x: i64\nstruct P: y: str\nP(1)");
+		let err = Session::new().execute(&[src]).unwrap_err().to_string();
+		assert!(err.contains("`P` is already declared in this scope"), "{err}");
+		assert!(err.contains("first declared here"), "{err}");
+		Ok(())
+	}
+}
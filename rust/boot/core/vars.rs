@@ -0,0 +1,209 @@
+use super::*;
+
+/// Variable declaration with its initializer expression as the single child.
+#[derive(Debug)]
+pub struct Let(pub Var);
+
+impl IsValue for Let {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 1 {
+			raise!(@node => "invalid let declaration with {} children", children.len());
+		}
+
+		let init = children[0].compile(ctx)?;
+		let init = Arena::get().store(init);
+		Ok(Code {
+			expr: Expr::Let(self.0, init),
+			span: node.span(),
+		})
+	}
+}
+
+/// Reference to a declared variable.
+#[derive(Debug)]
+pub struct VarRef(pub Var);
+
+impl IsValue for VarRef {
+	fn output_code(&self, _ctx: CodeContext, node: Node) -> Result<Code> {
+		Ok(Code {
+			expr: Expr::Var(self.0),
+			span: node.span(),
+		})
+	}
+}
+
+/// Parses `let name = expr` statements.
+///
+/// The declared name is bound from the end of the statement until the end
+/// of the enclosing scope.
+#[derive(Debug)]
+pub struct ParseLet;
+
+impl Eval for ParseLet {
+	fn precedence(&self) -> Precedence {
+		Precedence::LetDecl
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let symbols = Symbols::get();
+		for it in nodes {
+			let parent = if let Some(parent) = it.parent() {
+				parent
+			} else {
+				continue;
+			};
+
+			if it.index() != 0 {
+				raise!(@it => "`let` must be at the start of a statement");
+			}
+
+			let name = match it.next().and_then(|x| x.cast::<Token>()) {
+				Some(&Token::Word(name, ..)) => name,
+				_ => raise!(@it => "expected a name after `let`"),
+			};
+
+			let has_eq = parent.node(2).and_then(|x| x.cast::<Token>()).map(|x| x.symbol()) == Some(symbols.ASSIGN);
+			if !has_eq {
+				raise!(@it => "expected `=` after the name in `let` declaration");
+			}
+
+			let nodes = parent.remove_nodes(..);
+			for it in nodes.range(..3) {
+				it.set_done(true);
+			}
+
+			if nodes.len() == 3 {
+				raise!(@nodes.span() => "missing expression in `let` declaration");
+			}
+
+			let decl_span = nodes.range(..2).span();
+			let var = Var::new(name, decl_span);
+
+			let expr = nodes.range(3..);
+			let init = Node::new_at(Group, expr.span());
+			init.set_done(true);
+			init.append_nodes(expr);
+
+			let node = Node::new_at(Let(var), nodes.span());
+			node.set_done(true);
+			node.push_node(init);
+			parent.push_node(node);
+
+			let scope = if let Some(scope) = parent.scope() {
+				scope.span()
+			} else {
+				parent.span()
+			};
+
+			let span = Span::new(scope.source(), node.span().end(), scope.end());
+			WORDS.get(name).set_span(span, BindVar(var, node));
+		}
+		Ok(())
+	}
+}
+
+/// Binds variable names to their declaration.
+///
+/// The node is the declaration, used to check that variables are not used
+/// across function boundaries.
+#[derive(Debug)]
+pub struct BindVar(pub Var, pub Node);
+
+impl Eval for BindVar {
+	fn precedence(&self) -> Precedence {
+		Precedence::VarBinding
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let owner = FnDecl::owner(self.1);
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			if FnDecl::owner(*it) != owner {
+				raise!(@it => "variable {} is declared outside of the function", self.0);
+			}
+
+			it.set_done(true);
+			let node = Node::new_at(VarRef(self.0), it.span());
+			node.set_done(true);
+			it.replace([node]);
+		}
+		Ok(())
+	}
+}
+
+/// Assignment to a declared variable or a struct field, with the target and
+/// the value as children.
+#[derive(Debug)]
+pub struct Assign;
+
+impl IsValue for Assign {
+	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
+		let children = node.children();
+		if children.len() != 2 {
+			raise!(@node => "invalid assignment with {} children", children.len());
+		}
+
+		let target = children[0].compile(ctx)?;
+		let value = Arena::get().store(children[1].compile(ctx)?);
+		let expr = match target.expr {
+			Expr::Var(var) => Expr::Set(var, value),
+			Expr::Field(target, name) => Expr::SetField(target, name, value),
+			_ => raise!(@target => "invalid assignment target"),
+		};
+
+		Ok(Code {
+			expr,
+			span: node.span(),
+		})
+	}
+}
+
+/// Parses `target = expr` statements.
+#[derive(Debug)]
+pub struct ParseAssign;
+
+impl Eval for ParseAssign {
+	fn precedence(&self) -> Precedence {
+		Precedence::Assign
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		for it in nodes {
+			if it.done() {
+				continue;
+			}
+
+			let parent = if let Some(parent) = it.parent() {
+				parent
+			} else {
+				continue;
+			};
+
+			let index = it.index();
+			if index == 0 {
+				raise!(@it => "missing target for assignment");
+			}
+
+			if index == parent.len() - 1 {
+				raise!(@it => "missing value for assignment");
+			}
+
+			it.set_done(true);
+			let nodes = parent.remove_nodes(..);
+			let node = Node::new_at(Assign, nodes.span());
+			node.set_done(true);
+			for range in [nodes.range(..index), nodes.range(index + 1..)] {
+				let group = Node::new_at(Group, range.span());
+				group.set_done(true);
+				group.append_nodes(range);
+				node.push_node(group);
+			}
+			parent.push_node(node);
+		}
+		Ok(())
+	}
+}
@@ -4,6 +4,7 @@ use super::*;
 pub struct Cursor {
 	src: Source,
 	pos: usize,
+	end: usize,
 	row: usize,
 	col: usize,
 	ind: usize,
@@ -18,10 +19,18 @@ impl Cursor {
 			col: 0,
 			ind: 0,
 			pos: 0,
+			end: src.len(),
 			was_cr: false,
 		}
 	}
 
+	/// Cursor limited to the text of the given span.
+	pub fn for_span(span: Span) -> Self {
+		let mut cursor = span.location();
+		cursor.end = span.end();
+		cursor
+	}
+
 	#[inline(always)]
 	pub fn span_with(&self, len: usize) -> Span {
 		Span::new(self.src, self.pos, self.pos + len)
@@ -33,12 +42,12 @@ impl Cursor {
 
 	#[inline(always)]
 	pub fn text(&self) -> &'static str {
-		&self.src.text()[self.pos..]
+		&self.src.text()[self.pos..self.end]
 	}
 
 	#[inline(always)]
 	pub fn len(&self) -> usize {
-		self.src.len() - self.pos
+		self.end - self.pos
 	}
 
 	pub fn line(&self) -> usize {
@@ -0,0 +1,49 @@
+use super::*;
+
+/// Non-fatal diagnostics (e.g. warnings) for the current compilation.
+///
+/// Evaluators and compilation passes push diagnostics here instead of
+/// failing, and the [`Session`] takes them at the end of each stage.
+#[derive(Default)]
+pub struct Diagnostics {
+	list: Mutex<Vec<Error>>,
+}
+
+impl Diagnostics {
+	pub fn get() -> &'static Self {
+		static DIAGNOSTICS: Init<Diagnostics> = Init::default();
+		DIAGNOSTICS.get()
+	}
+
+	/// Adds a diagnostic for the current compilation. Error lists are added
+	/// as separate diagnostics.
+	///
+	/// Errors are also accepted, in which case the compilation fails once
+	/// the diagnostics are checked. This allows reporting multiple errors.
+	///
+	/// Diagnostics with the same severity, message, and location as a
+	/// previous one are ignored, since the same code can be checked more
+	/// than once (e.g. generic functions).
+	pub fn push(diagnostic: Error) {
+		let key = |err: &Error| (err.severity(), err.to_string());
+		let mut list = Self::get().list.lock().unwrap();
+		for it in diagnostic.split() {
+			let it_key = key(&it);
+			if !list.iter().any(|x| key(x) == it_key) {
+				list.push(it);
+			}
+		}
+	}
+
+	/// Removes and returns all pending diagnostics, in the order they were
+	/// added.
+	pub fn take() -> Vec<Error> {
+		let mut list = Self::get().list.lock().unwrap();
+		std::mem::take(&mut *list)
+	}
+
+	/// Discards any pending diagnostics.
+	pub fn clear() {
+		Self::get().list.lock().unwrap().clear();
+	}
+}
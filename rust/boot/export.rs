@@ -0,0 +1,208 @@
+use super::*;
+
+/// Exports of the node tree for external viewers.
+///
+/// Nodes are labeled with their description, span, and whether they are
+/// done, along with the evals that created or changed them if those were
+/// recorded by the [`Trace`].
+impl Node {
+	/// Writes the node tree as a Graphviz `dot` graph.
+	pub fn write_dot(&self, out: &mut Writer) -> Result<()> {
+		writeln!(out, "digraph program {{")?;
+		let style = r#"shape=box, style=filled, fontname="monospace", fontsize=10"#;
+		writeln!(out, "\tnode [{style}];")?;
+		let mut count = 0;
+		self.write_dot_node(out, &mut count)?;
+		writeln!(out, "}}")?;
+		Ok(())
+	}
+
+	/// Writes the node tree as a self-contained HTML page, with collapsible
+	/// nodes and the source text highlighted when hovering over a node.
+	pub fn write_html(&self, out: &mut Writer) -> Result<()> {
+		let mut sources = Vec::new();
+		let mut tree = String::new();
+		self.write_html_node(&mut Writer::fmt(&mut tree), &mut sources)?;
+
+		writeln!(out, "<!DOCTYPE html>")?;
+		writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+		writeln!(out, "<title>Program tree</title>")?;
+		writeln!(out, "<style>{HTML_STYLE}</style>\n</head>\n<body>")?;
+		writeln!(out, "<div id=\"tree\">\n{tree}</div>")?;
+		writeln!(out, "<div id=\"sources\">")?;
+		for (n, src) in sources.iter().enumerate() {
+			let name = html_escape(src.name());
+			let text = html_escape(src.text());
+			writeln!(out, "<h3>{name}</h3>\n<pre id=\"src-{n}\">{text}</pre>")?;
+		}
+		writeln!(out, "</div>")?;
+		writeln!(out, "<script>{HTML_SCRIPT}</script>\n</body>\n</html>")?;
+		Ok(())
+	}
+
+	fn write_dot_node(&self, out: &mut Writer, count: &mut usize) -> Result<usize> {
+		let id = *count;
+		*count += 1;
+
+		let mut label = dot_escape(&single_line(self.description()));
+		let span = self.span();
+		if !span.is_empty() {
+			label.push_str(&format!("\\l{}", dot_escape(&span.to_string())));
+		}
+		for it in Trace::history(*self) {
+			label.push_str(&format!("\\lvia {}", dot_escape(&it)));
+		}
+
+		let color = if self.done() { "#e8f0fe" } else { "#fde2e2" };
+		writeln!(out, "\tn{id} [label=\"{label}\\l\", fillcolor=\"{color}\"];")?;
+		for it in self.children() {
+			let child = it.write_dot_node(out, count)?;
+			writeln!(out, "\tn{id} -> n{child};")?;
+		}
+		Ok(id)
+	}
+
+	fn write_html_node(&self, out: &mut Writer, sources: &mut Vec<Source>) -> Result<()> {
+		let span = self.span();
+		let class = if self.done() { "done" } else { "pending" };
+		let mut attrs = format!("class=\"{class}\"");
+		if !span.is_empty() {
+			let src = span.source();
+			let index = match sources.iter().position(|x| *x == src) {
+				Some(index) => index,
+				None => {
+					sources.push(src);
+					sources.len() - 1
+				}
+			};
+
+			// offsets are in UTF-16 units, as used by JavaScript strings
+			let text = src.text();
+			let sta = text[..span.sta()].encode_utf16().count();
+			let end = text[..span.end()].encode_utf16().count();
+			attrs.push_str(&format!(" data-src=\"{index}\" data-sta=\"{sta}\" data-end=\"{end}\""));
+		}
+
+		let mut label = html_escape(&single_line(self.description()));
+		if !span.is_empty() {
+			let span = html_escape(&span.to_string());
+			label.push_str(&format!(" <span class=\"span\">{span}</span>"));
+		}
+		if !self.done() {
+			label.push_str(" <span class=\"state\">pending</span>");
+		}
+		for it in Trace::history(*self) {
+			label.push_str(&format!(" <span class=\"eval\">{}</span>", html_escape(&it)));
+		}
+
+		let children = self.children();
+		if children.len() == 0 {
+			writeln!(out, "<div {attrs}>{label}</div>")?;
+		} else {
+			writeln!(out, "<details open>\n<summary {attrs}>{label}</summary>")?;
+			for it in children {
+				it.write_html_node(out, sources)?;
+			}
+			writeln!(out, "</details>")?;
+		}
+		Ok(())
+	}
+}
+
+const HTML_STYLE: &str = "
+body { display: flex; gap: 1em; margin: 0; font: 13px monospace; }
+#tree { flex: 1; padding: 1em; overflow: auto; height: 100vh; box-sizing: border-box; }
+#sources { flex: 1; padding: 1em; overflow: auto; height: 100vh; box-sizing: border-box; background: #f6f8fa; }
+details > :not(summary) { margin-left: 1.5em; }
+div.done, div.pending { margin-left: 1.2em; }
+summary, div.done, div.pending { cursor: default; padding: 1px 0; }
+summary:hover, div.done:hover, div.pending:hover { background: #e8f0fe; }
+.pending { color: #b00020; }
+.span { color: #888; }
+.state { color: #fff; background: #b00020; padding: 0 3px; border-radius: 3px; }
+.eval { color: #1a7f37; }
+.eval::before { content: '\\2190  '; }
+mark { background: #ffd33d; }
+";
+
+const HTML_SCRIPT: &str = "
+const sources = [...document.querySelectorAll('#sources pre')];
+const texts = sources.map(x => x.textContent);
+const escape = s => s.replace(/[&<>]/g, c => ({ '&': '&amp;', '<': '&lt;', '>': '&gt;' })[c]);
+function clear() {
+	sources.forEach((pre, n) => pre.textContent = texts[n]);
+}
+function highlight(el) {
+	clear();
+	const n = +el.dataset.src, sta = +el.dataset.sta, end = +el.dataset.end;
+	const text = texts[n];
+	sources[n].innerHTML = escape(text.slice(0, sta)) + '<mark>' + escape(text.slice(sta, end)) + '</mark>' + escape(text.slice(end));
+	sources[n].querySelector('mark').scrollIntoView({ block: 'nearest' });
+}
+document.querySelectorAll('[data-src]').forEach(el => {
+	el.addEventListener('mouseover', ev => { ev.stopPropagation(); highlight(el); });
+	el.addEventListener('mouseout', clear);
+});
+";
+
+fn dot_escape(text: &str) -> String {
+	let mut output = String::new();
+	for chr in text.chars() {
+		match chr {
+			'"' => output.push_str("\\\""),
+			'\\' => output.push_str("\\\\"),
+			'\n' => output.push_str("\\l"),
+			_ => output.push(chr),
+		}
+	}
+	output
+}
+
+fn html_escape(text: &str) -> String {
+	let mut output = String::new();
+	for chr in text.chars() {
+		match chr {
+			'&' => output.push_str("&amp;"),
+			'<' => output.push_str("&lt;"),
+			'>' => output.push_str("&gt;"),
+			'"' => output.push_str("&quot;"),
+			_ => output.push(chr),
+		}
+	}
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn export_tree() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("export", "a <b>");
+		let tokens = core_lexer().tokenize(&mut Cursor::new(src))?;
+
+		let root = Node::new_at(Program, Span::empty());
+		root.append_nodes(tokens.iter().map(|x| Node::new_at(x.symbol().as_str(), x.span())));
+		root.set_done(true);
+		root.node(0).unwrap().set_done(true);
+
+		let mut dot = String::new();
+		root.write_dot(&mut Writer::fmt(&mut dot))?;
+		assert!(dot.starts_with("digraph program {\n"));
+		assert!(dot.contains(r##"n1 [label="\"a\"\lexport:1:1+1\l", fillcolor="#e8f0fe"];"##));
+		assert!(dot.contains(r##"n2 [label="\"<\"\lexport:1:3+1\l", fillcolor="#fde2e2"];"##));
+		assert!(dot.contains("\tn0 -> n1;\n"));
+
+		let mut html = String::new();
+		root.write_html(&mut Writer::fmt(&mut html))?;
+		assert!(html.contains(r#"<div class="done" data-src="0" data-sta="0" data-end="1">&quot;a&quot; <span"#));
+		assert!(html.contains("<span class=\"state\">pending</span>"));
+		assert!(html.contains("<pre id=\"src-0\">a &lt;b&gt;</pre>"));
+
+		Node::clear_pending();
+		Ok(())
+	}
+}
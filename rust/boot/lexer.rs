@@ -28,8 +28,22 @@ impl Eval for Lexer {
 			if let Some(src) = it.cast::<Source>() {
 				it.set_done(true);
 
+				// lexical errors don't stop the compilation, so that all
+				// of them are reported at once
 				let mut cursor = Cursor::new(*src);
-				let tokens = self.tokenize(&mut cursor)?;
+				let (mut tokens, mut errors) = self.tokenize_with_errors(&mut cursor);
+
+				// operators declared in the source are added to its lexer, so
+				// tokenize again with them
+				let declared = declared_symbols(*src, &tokens);
+				if declared.iter().any(|x| self.symbols.query(x).is_none()) {
+					self.add_symbols(declared);
+					cursor = Cursor::new(*src);
+					(tokens, errors) = self.tokenize_with_errors(&mut cursor);
+				}
+				if let Some(errors) = errors {
+					Diagnostics::push(errors);
+				}
 
 				if cursor.len() > 0 {
 					assert!(cursor.len() == 0,);
@@ -70,7 +84,26 @@ impl Lexer {
 		}
 	}
 
+	/// Tokenizes the input, returning all lexical errors together.
 	pub fn tokenize(&self, cursor: &mut Cursor) -> Result<Vec<Token>> {
+		let (tokens, errors) = self.tokenize_with_errors(cursor);
+		match errors {
+			Some(errors) => Err(errors),
+			None => Ok(tokens),
+		}
+	}
+
+	/// Tokenizes the input, recovering from invalid input with a
+	/// [`Token::Error`] and returning the list of errors, if any.
+	pub fn tokenize_with_errors(&self, cursor: &mut Cursor) -> (Vec<Token>, Option<Error>) {
+		let mut errors: Option<Error> = None;
+		let mut push_error = |err: Error| {
+			errors = Some(match errors.take() {
+				Some(errors) => errors.append(err),
+				None => err,
+			});
+		};
+
 		let mut output = Vec::new();
 		while cursor.len() > 0 {
 			let text = cursor.text();
@@ -95,23 +128,38 @@ impl Lexer {
 			let token = if let Some('\r' | '\n') = text.chars().next() {
 				let len = if text.starts_with("\r\n") { 2 } else { 1 };
 				Token::Break(cursor.span_with(len))
-			} else if let Some(token) = self.match_next(cursor) {
+			} else if let Some(token) = self.match_next(cursor, &mut push_error) {
 				token
+			} else if let Some(symbol) = self.symbols.read(text) {
+				Token::Symbol(symbol, cursor.span_with(symbol.len()))
 			} else {
-				if let Some(symbol) = self.symbols.read(text) {
-					Token::Symbol(symbol, cursor.span_with(symbol.len()))
-				} else {
-					return raise!(@cursor => "invalid token");
-				}
+				let span = cursor.span_with(self.invalid_len(text));
+				push_error(err!(@span => "invalid token `{}`", span.text()));
+				Token::Error(span)
 			};
 
 			output.push(token);
 			cursor.skip_len(token.span().len());
 		}
-		Ok(output)
+		(output, errors)
+	}
+
+	/// Length of invalid input at the start of the text, up to the next
+	/// position where a valid token could start.
+	fn invalid_len(&self, text: &str) -> usize {
+		for (pos, chr) in text.char_indices().skip(1) {
+			let rest = &text[pos..];
+			let is_break = is_space(chr) || matches!(chr, '\r' | '\n' | '#');
+			let is_token = is_ident(chr, false) || string_delimiter(rest).is_some();
+			let is_symbol = self.symbols.read(rest).is_some();
+			if is_break || is_token || is_symbol {
+				return pos;
+			}
+		}
+		text.len()
 	}
 
-	fn match_next(&self, cursor: &Cursor) -> Option<Token> {
+	fn match_next<F: FnMut(Error)>(&self, cursor: &Cursor, mut push_error: F) -> Option<Token> {
 		let text = cursor.text();
 		let next = text.chars().next().unwrap();
 		let token = if next == '#' {
@@ -123,23 +171,40 @@ impl Lexer {
 				}
 			}
 			Token::Comment(cursor.span_with(len))
-		} else if next == '\'' || next == '"' {
-			let quote = next;
-			let can_escape = true;
+		} else if let Some((open, delim, raw)) = string_delimiter(text) {
+			// only triple-quoted strings can span multiple lines
+			let multiline = delim.len() > 1;
 			let mut escape = false;
-			let mut len = text.len();
-			for (pos, chr) in text.char_indices() {
-				if chr == quote && pos > 0 && !escape {
-					len = pos + chr.len_utf8();
-					break;
-				}
+			let mut len = None;
+			for (pos, chr) in text[open..].char_indices() {
 				if escape {
 					escape = false;
-				} else if can_escape && chr == '\\' {
+				} else if !multiline && (chr == '\n' || chr == '\r') {
+					break;
+				} else if !raw && chr == '\\' {
 					escape = true;
+				} else if text[open + pos..].starts_with(delim) {
+					len = Some(open + pos + delim.len());
+					break;
+				}
+			}
+
+			match len {
+				Some(len) => Token::Literal(cursor.span_with(len)),
+				None => {
+					let span = cursor.span_with(open);
+					let err = err!(@span => "unterminated string literal, expected a closing `{delim}`");
+					push_error(err.with_help("use `\"\"\"` for strings spanning multiple lines"));
+
+					// skip the rest of the string
+					let len = if multiline {
+						text.len()
+					} else {
+						text.find(['\r', '\n']).unwrap_or(text.len())
+					};
+					Token::Error(cursor.span_with(len))
 				}
 			}
-			Token::Literal(cursor.span_with(len))
 		} else if is_digit(next) {
 			let len = count_digits(text);
 			let (len, flt) = if text[len..].starts_with(".") {
@@ -202,6 +267,27 @@ impl Lexer {
 	}
 }
 
+/// Returns the length of the opening delimiter for a string literal at the
+/// start of the text, the closing delimiter, and if the string is raw.
+pub fn string_delimiter(text: &str) -> Option<(usize, &'static str, bool)> {
+	let (raw, rest) = match text.strip_prefix('r') {
+		Some(rest) => (true, rest),
+		None => (false, text),
+	};
+
+	let delim = if rest.starts_with("\"\"\"") {
+		"\"\"\""
+	} else if rest.starts_with('"') {
+		"\""
+	} else if rest.starts_with('\'') {
+		"'"
+	} else {
+		return None;
+	};
+
+	Some((text.len() - rest.len() + delim.len(), delim, raw))
+}
+
 impl Debug for Lexer {
 	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
 		write!(f, "DefaultLexer")
@@ -606,6 +692,90 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn raw_and_multiline_strings() -> Result<()> {
+		let input = source(
+			[
+				r#"r'a\' r"b\" r x"#,
+				r#""""a "quoted" \""" b""""#,
+				r#"""""#,
+				r#"  line 1"#,
+				r#"  line 2"#,
+				r#"""" r"""\""""#,
+			]
+			.join("\n"),
+		);
+
+		let result = tokenize(input)?;
+		assert_eq!(
+			vec![
+				r#"literal(r'a\')"#,
+				r#"literal(r"b\")"#,
+				"word(r)",
+				"word(x)",
+				"eol",
+				r#"literal("""a "quoted" \""" b""")"#,
+				"eol",
+				"literal(\"\"\"\n  line 1\n  line 2\n\"\"\")",
+				r#"literal(r"""\""")"#,
+			],
+			result
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn unterminated_strings() {
+		let tests = [
+			("'abc\n'", "'"),
+			("x \"abc", "\""),
+			("'abc\\'", "'"),
+			("r'abc", "'"),
+			("\"\"\"abc\"\"", "\"\"\""),
+		];
+		for (text, delim) in tests {
+			let err = tokenize(source(text)).unwrap_err().to_string();
+			let msg = format!("unterminated string literal, expected a closing `{delim}`");
+			assert!(err.contains(&msg), "{text:?}: {err}");
+		}
+	}
+
+	#[test]
+	fn error_recovery() {
+		let lexer = Lexer::new();
+		lexer.add_symbols(["+", "="]);
+
+		let mut cursor = Cursor::new(source("a $ b 'x\nc = @@d + \\"));
+		let (tokens, errors) = lexer.tokenize_with_errors(&mut cursor);
+		let tokens = tokens.iter().map(|x| format!("{x}:{}", x.span().text()));
+		let tokens = tokens.collect::<Vec<_>>();
+		assert_eq!(
+			tokens,
+			[
+				"word(a):a",
+				"error:$",
+				"word(b):b",
+				"error:'x",
+				"break:\n",
+				"word(c):c",
+				"symbol(\"=\"):=",
+				"error:@@",
+				"word(d):d",
+				"symbol(\"+\"):+",
+				"error:\\",
+			]
+		);
+
+		let errors = errors.unwrap().split();
+		let errors = errors.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+		assert_eq!(errors.len(), 4);
+		assert!(errors[0].starts_with("error: invalid token `$`"));
+		assert!(errors[1].starts_with("error: unterminated string literal"));
+		assert!(errors[2].starts_with("error: invalid token `@@`"));
+		assert!(errors[3].starts_with("error: invalid token `\\`"));
+	}
+
 	fn tokenize(src: Source) -> Result<Vec<&'static str>> {
 		let lexer = Lexer::new();
 		lexer.add_symbols(["+", "++", "-", "--", "<", "<<", "<<<", "=", "==", ",", "."]);
@@ -626,6 +796,7 @@ mod tests {
 				Token::Float(_) => format!("float({span})"),
 				Token::Literal(_) => format!("literal({span})"),
 				Token::Comment(_) => format!("comment({span})"),
+				Token::Error(_) => format!("error({span})"),
 			};
 			out.push(Box::leak(Box::new(text)).as_str());
 		}
@@ -149,6 +149,12 @@ impl<T: Clone> Default for List<T> {
 	}
 }
 
+impl<T: Clone + Debug> Debug for List<T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_list().entries(self.items()).finish()
+	}
+}
+
 struct ListData<T: 'static> {
 	inner: RwLock<&'static [T]>,
 }
@@ -31,6 +31,16 @@ impl NodeList {
 		Span::for_range(self.items)
 	}
 
+	/// Returns the span for the nodes or, for an empty list taken from a
+	/// [`NodeList::range`], the position of the range.
+	pub fn span_or_pos(&self) -> Span {
+		if self.items.is_empty() {
+			self.span
+		} else {
+			self.span()
+		}
+	}
+
 	#[inline(always)]
 	pub fn range<T: RangeBounds<usize>>(&self, range: T) -> NodeList {
 		let sta = match range.start_bound() {
@@ -43,7 +53,7 @@ impl NodeList {
 			std::ops::Bound::Excluded(&n) => n,
 			std::ops::Bound::Unbounded => self.items.len(),
 		};
-		debug_assert!(end <= self.items.len() && sta < end);
+		debug_assert!(end <= self.items.len() && sta <= end);
 
 		let span = Span::for_slice(self.items, &range, self.span);
 		let items = &self.items[sta..end];
@@ -69,7 +79,7 @@ impl std::ops::Index<usize> for NodeList {
 	}
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Node {
 	data: NonNull<NodeData>,
 }
@@ -119,10 +129,23 @@ impl Node {
 			}
 		}
 
+		Trace::created(node);
 		value.get().bind(node);
 		node
 	}
 
+	/// Discards the list of pending nodes without checking it.
+	pub fn clear_pending() {
+		PENDING_NODES.store(std::ptr::null_mut(), Order::Release);
+	}
+
+	/// Checks that all nodes created since the last check have been solved.
+	///
+	/// Unsolved nodes are grouped by their innermost solved ancestor, usually
+	/// the line they are in, and nodes nested inside an unsolved node belong
+	/// to its group. Each group is reported once, at its first node, with the
+	/// other nodes summarized in notes. Groups with the same description in
+	/// a source are reported once, with the other occurrences as labels.
 	pub fn check_pending() -> Result<()> {
 		if !CHECK_PENDING {
 			return Ok(());
@@ -132,53 +155,128 @@ impl Node {
 			.fetch_update(Order::Release, Order::Acquire, |_| Some(std::ptr::null_mut()))
 			.unwrap();
 
-		let mut total = 0;
-		let mut pending_by_type: HashMap<TypeId, Vec<Node>> = HashMap::new();
+		let mut nodes = Vec::new();
 		while let Some(data) = NonNull::new(pending) {
 			let node = Node { data };
 			if !node.done() {
-				let key = node.value().value_type();
-				let entries = pending_by_type.entry(key).or_default();
-				entries.push(node);
-				total += 1;
+				nodes.push(node);
 			}
 			pending = node.data().next_pending.load(Order::Relaxed);
 		}
 
-		const MAX_NODES: usize = 30;
+		// the outermost unsolved node in a chain of unsolved parents is the
+		// top of the group, under the parent that has been solved
+		let unsolved = nodes.iter().map(|x| x.data).collect::<HashSet<_>>();
+		let mut by_parent: HashMap<NonNull<NodeData>, (Vec<Node>, Vec<Node>)> = HashMap::new();
+		for node in nodes.iter().copied() {
+			let mut top = node;
+			while let Some(parent) = top.parent().filter(|x| unsolved.contains(&x.data)) {
+				top = parent;
+			}
 
-		let pending = pending_by_type;
-		if total > 0 {
-			let max_per = (MAX_NODES / pending.len()).max(1);
-			let max_len = max_per * pending.len();
-			let mut err = String::new();
-			let (s, have) = if total != 1 { ("s", "have") } else { ("", "has") };
+			let key = top.parent().unwrap_or(top).data;
+			let (tops, nested) = by_parent.entry(key).or_default();
+			if top == node {
+				tops.push(node);
+			} else {
+				nested.push(node);
+			}
+		}
 
-			{
-				let mut msg = Writer::fmt(&mut err);
-
-				for (n, nodes) in pending.into_iter().map(|x| x.1).enumerate() {
-					if n > 0 {
-						let _ = write!(msg, "\n");
-					}
-					for node in nodes.into_iter().take(max_per) {
-						let _ = write!(msg, "\n=> ");
-						let _ = node.value().describe(&mut msg);
-						let _ = node.write_pos(&mut msg, "\n   … at ");
-					}
+		struct Root {
+			node: Node,
+			siblings: Vec<Node>,
+			nested: Vec<Node>,
+		}
+
+		let mut roots = Vec::new();
+		for (_, (mut tops, nested)) in by_parent {
+			tops.sort_by_key(|x| x.span());
+			let siblings = tops.split_off(1);
+			let node = tops[0];
+			roots.push(Root { node, siblings, nested });
+		}
+		roots.sort_by_key(|x| x.node.span());
+
+		// roots with the same description are usually the same root cause,
+		// such as an undefined name used more than once
+		let mut groups: Vec<(String, Root, Vec<Node>)> = Vec::new();
+		for root in roots {
+			let (desc, src) = (root.node.description(), root.node.source());
+			let same = groups.iter_mut().find(|x| x.0 == desc && x.1.node.source() == src);
+			match same {
+				Some((_, _, others)) => others.push(root.node),
+				None => groups.push((desc, root, Vec::new())),
+			}
+		}
+
+		// a source with many unsolved nodes would hide the errors for others
+		const MAX_BY_SRC: usize = 20;
+		const MAX_NESTED: usize = 5;
+
+		let mut skipped = 0;
+		let mut by_source: Vec<(Source, usize)> = Vec::new();
+		let mut errors = Vec::new();
+		for (desc, root, others) in groups {
+			let Root {
+				node,
+				siblings,
+				mut nested,
+			} = root;
+			let src = node.source();
+			let count = match by_source.iter().position(|x| x.0 == src) {
+				Some(index) => &mut by_source[index].1,
+				None => {
+					by_source.push((src, 0));
+					&mut by_source.last_mut().unwrap().1
 				}
+			};
+			if *count >= MAX_BY_SRC {
+				skipped += 1 + others.len() + siblings.len() + nested.len();
+				continue;
+			}
+			*count += 1;
+
+			let mut err = err!(@node => "{desc} has not been solved");
+			for it in others {
+				err = err.with_label(it, "also unsolved here");
+			}
 
-				if total > max_len {
-					let cnt = total - max_len;
-					let s = if cnt > 1 { "s" } else { "" };
-					let _ = write!(msg, "\n\n…skipping remaining {cnt} node{s}");
+			let summary = |nodes: Vec<Node>| {
+				let len = nodes.len();
+				let list = nodes.into_iter().take(MAX_NESTED).map(|x| x.description());
+				let mut list = list.collect::<Vec<_>>();
+				if len > MAX_NESTED {
+					list.push("…".to_string());
 				}
+				let s = if len > 1 { "s" } else { "" };
+				(len, s, list.join(", "))
+			};
+
+			if !siblings.is_empty() {
+				let (len, s, list) = summary(siblings);
+				let note = format!("{len} other node{s} in the same expression could not be solved: {list}");
+				err = err.with_note(note);
 			}
 
-			raise!("{total} node{s} {have} not been solved:{err}");
+			if !nested.is_empty() {
+				nested.sort_by_key(|x| x.span());
+				let (len, s, list) = summary(nested);
+				let note = format!("{len} nested node{s} could not be solved because of this: {list}");
+				err = err.with_note(note);
+			}
+			errors.push(err);
 		}
 
-		Ok(())
+		if skipped > 0 {
+			let s = if skipped > 1 { "s" } else { "" };
+			errors.push(err!("skipping remaining {skipped} unsolved node{s}"));
+		}
+
+		match errors.into_iter().reduce(Error::append) {
+			Some(errors) => Err(errors),
+			None => Ok(()),
+		}
 	}
 
 	pub fn send(&self, msg: Message) -> Result<bool> {
@@ -277,6 +375,17 @@ impl Node {
 		data.parent.get()
 	}
 
+	pub fn scope(&self) -> Option<Node> {
+		let mut cur = self.parent();
+		while let Some(node) = cur {
+			if node.value().is_scope() {
+				return Some(node);
+			}
+			cur = node.parent();
+		}
+		None
+	}
+
 	#[inline(always)]
 	pub fn index(&self) -> usize {
 		let data = self.data();
@@ -305,8 +414,9 @@ impl Node {
 		T::IntoIter: ExactSizeIterator,
 	{
 		if let Some(parent) = self.parent() {
+			// don't use `remove` since it would drop an empty collection parent
 			let index = self.index();
-			self.remove();
+			parent.remove_nodes(index..index + 1);
 			parent.insert_nodes(index, nodes);
 		}
 	}
@@ -442,6 +552,13 @@ impl Node {
 		Ok(())
 	}
 
+	/// Short description of the node value, as used in errors.
+	pub fn description(&self) -> String {
+		let mut desc = String::new();
+		let _ = self.value().describe(&mut Writer::fmt(&mut desc));
+		desc
+	}
+
 	pub fn write_with_pos(&self, f: &mut Writer) -> Result<()> {
 		self.write(f)?;
 		self.write_pos(f, "\n… at ")?;
@@ -0,0 +1,344 @@
+use super::*;
+
+/// Matcher for a single item in a [`Pattern`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Match {
+	/// Any node.
+	Any,
+	/// Word token with the given symbol.
+	Word(Symbol),
+	/// Any word token.
+	AnyWord,
+	/// Symbol token with the given symbol.
+	Symbol(Symbol),
+	/// Node with a value of the given type.
+	Value(TypeId),
+	/// Any number of nodes, including none.
+	///
+	/// This matches as few nodes as possible for the rest of the pattern to
+	/// match, except at the end of the pattern where it matches all the
+	/// remaining siblings.
+	Rest,
+}
+
+impl Match {
+	pub fn word<T: Into<Symbol>>(word: T) -> Self {
+		Match::Word(word.into())
+	}
+
+	pub fn symbol<T: Into<Symbol>>(symbol: T) -> Self {
+		Match::Symbol(symbol.into())
+	}
+
+	pub fn value<T: IsValue>() -> Self {
+		Match::Value(TypeId::of::<T>())
+	}
+
+	/// Returns true if the node matches. [`Match::Rest`] matches any node.
+	pub fn matches(&self, node: Node) -> bool {
+		match *self {
+			Match::Any | Match::Rest => true,
+			Match::Word(word) => matches!(node.cast::<Token>(), Some(&Token::Word(x, ..)) if x == word),
+			Match::AnyWord => matches!(node.cast::<Token>(), Some(Token::Word(..))),
+			Match::Symbol(symbol) => matches!(node.cast::<Token>(), Some(&Token::Symbol(x, ..)) if x == symbol),
+			Match::Value(kind) => node.value().value_type() == kind,
+		}
+	}
+}
+
+/// Sequence of [`Match`] items for a node and its following siblings, such
+/// as `let name = expr...`:
+///
+/// ```ignore
+/// let pattern = Pattern::new([Match::word("let"), Match::AnyWord, Match::symbol("="), Match::Rest]);
+/// ```
+///
+/// A successful match returns the nodes captured by each item, which can
+/// then be replaced with new nodes created using [`Build`]. The matched
+/// nodes must be removed before being added to the new nodes.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+	items: Vec<Match>,
+}
+
+impl Pattern {
+	pub fn new<T: IntoIterator<Item = Match>>(items: T) -> Self {
+		let items = items.into_iter().collect();
+		Self { items }
+	}
+
+	/// Matches the pattern starting at the node and continuing with its
+	/// next siblings. Returns [`None`] if the node has no parent or the
+	/// pattern does not match.
+	pub fn match_at(&self, node: Node) -> Option<Captures> {
+		let parent = node.parent()?;
+		let list = parent.children();
+		let mut ranges = Vec::with_capacity(self.items.len());
+		if Self::match_items(&self.items, list.as_slice(), node.index(), &mut ranges) {
+			let items = self.items.clone();
+			Some(Captures {
+				parent,
+				list,
+				items,
+				ranges,
+			})
+		} else {
+			None
+		}
+	}
+
+	fn match_items(items: &[Match], nodes: &[Node], pos: usize, ranges: &mut Vec<(usize, usize)>) -> bool {
+		let (item, rest) = match items.split_first() {
+			Some(items) => items,
+			None => return true,
+		};
+
+		if *item == Match::Rest {
+			if rest.is_empty() {
+				ranges.push((pos, nodes.len()));
+				return true;
+			}
+
+			for end in pos..=nodes.len() {
+				ranges.push((pos, end));
+				if Self::match_items(rest, nodes, end, ranges) {
+					return true;
+				}
+				ranges.pop();
+			}
+			false
+		} else if pos < nodes.len() && item.matches(nodes[pos]) {
+			ranges.push((pos, pos + 1));
+			if Self::match_items(rest, nodes, pos + 1, ranges) {
+				return true;
+			}
+			ranges.pop();
+			false
+		} else {
+			false
+		}
+	}
+}
+
+/// Nodes captured by a successful [`Pattern`] match, one list for each
+/// item in the pattern.
+pub struct Captures {
+	parent: Node,
+	list: NodeList,
+	items: Vec<Match>,
+	ranges: Vec<(usize, usize)>,
+}
+
+impl Captures {
+	/// Returns the nodes captured by the item at the index. For an empty
+	/// [`Match::Rest`], the list span is the position where it matched.
+	pub fn get(&self, index: usize) -> NodeList {
+		let (sta, end) = self.ranges[index];
+		self.list.range(sta..end)
+	}
+
+	/// Returns the node captured by a single node item at the index.
+	pub fn node(&self, index: usize) -> Node {
+		let (sta, end) = self.ranges[index];
+		assert!(end == sta + 1, "capture {index} is not a single node");
+		self.list[sta]
+	}
+
+	/// Returns all the nodes matched by the pattern.
+	pub fn nodes(&self) -> NodeList {
+		let (sta, end) = self.bounds();
+		self.list.range(sta..end)
+	}
+
+	pub fn span(&self) -> Span {
+		self.nodes().span_or_pos()
+	}
+
+	/// Marks the nodes matched by single node items as done, such as the
+	/// keywords and symbols in the pattern. Nodes captured by [`Match::Rest`]
+	/// are left as they are.
+	pub fn set_done(&self) {
+		let ranges = self.items.iter().zip(self.ranges.iter());
+		for (_, &(sta, _)) in ranges.filter(|(item, _)| **item != Match::Rest) {
+			self.list[sta].set_done(true);
+		}
+	}
+
+	/// Removes the matched nodes from their parent, so they can be added
+	/// to the nodes replacing them. The captures are still valid after this.
+	pub fn remove(&self) -> NodeList {
+		let (sta, end) = self.bounds();
+		self.parent.remove_nodes(sta..end)
+	}
+
+	/// Inserts nodes in the parent where the pattern matched, usually to
+	/// replace the nodes after [`Captures::remove`].
+	pub fn insert<T: IntoIterator<Item = Node>>(&self, nodes: T)
+	where
+		T::IntoIter: ExactSizeIterator,
+	{
+		let (sta, _) = self.bounds();
+		self.parent.insert_nodes(sta, nodes);
+	}
+
+	fn bounds(&self) -> (usize, usize) {
+		let sta = self.ranges.first().map(|x| x.0).unwrap_or_default();
+		let end = self.ranges.last().map(|x| x.1).unwrap_or(sta);
+		(sta, end)
+	}
+}
+
+impl NodeList {
+	/// Splits the list at each node matching the separator, which are not
+	/// included in the parts. An empty list has no parts.
+	///
+	/// Empty parts have the position of the separator (or the end of the
+	/// list) as their span, see [`NodeList::span_or_pos`].
+	pub fn split(&self, separator: Match) -> Vec<NodeList> {
+		let mut parts = Vec::new();
+		if self.len() == 0 {
+			return parts;
+		}
+
+		let mut sta = 0;
+		for (n, it) in self.into_iter().enumerate() {
+			if separator.matches(it) {
+				parts.push(self.range(sta..n));
+				sta = n + 1;
+			}
+		}
+		parts.push(self.range(sta..));
+		parts
+	}
+}
+
+/// Builder for a node created by an eval to replace the nodes it parsed.
+///
+/// The node is marked as done and, unless given, its span covers its
+/// children.
+pub struct Build {
+	value: Value,
+	span: Option<Span>,
+	children: Vec<Node>,
+}
+
+impl Build {
+	pub fn new<T: Into<Value>>(value: T) -> Self {
+		Self {
+			value: value.into(),
+			span: None,
+			children: Vec::new(),
+		}
+	}
+
+	pub fn at(mut self, span: Span) -> Self {
+		self.span = Some(span);
+		self
+	}
+
+	pub fn child(mut self, node: Node) -> Self {
+		self.children.push(node);
+		self
+	}
+
+	pub fn children<T: IntoIterator<Item = Node>>(mut self, nodes: T) -> Self {
+		self.children.extend(nodes);
+		self
+	}
+
+	/// Adds a [`Group`] child with the nodes. An empty group has the
+	/// position of the list, see [`NodeList::span_or_pos`].
+	pub fn group(self, nodes: NodeList) -> Self {
+		let group = Build::new(Group).at(nodes.span_or_pos()).children(nodes);
+		self.child(group.node())
+	}
+
+	pub fn node(self) -> Node {
+		let span = match self.span {
+			Some(span) => span,
+			None => Span::for_range(&self.children),
+		};
+		let node = Node::new_at(self.value, span);
+		node.set_done(true);
+		node.append_nodes(self.children);
+		node
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(text: &'static str) -> Result<Node> {
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("pattern", text);
+		let tokens = core_lexer().tokenize(&mut Cursor::new(src))?;
+		let root = Node::new_at(Group, src.span());
+		root.append_nodes(tokens.iter().map(|x| Node::new(*x)));
+		Ok(root)
+	}
+
+	#[test]
+	fn match_and_replace() -> Result<()> {
+		let _lock = init_test();
+
+		let root = parse("x let a = 1 + 2")?;
+		let pattern = Pattern::new([Match::word("let"), Match::AnyWord, Match::symbol("="), Match::Rest]);
+		assert!(pattern.match_at(root.node(0).unwrap()).is_none());
+
+		let found = pattern.match_at(root.node(1).unwrap()).unwrap();
+		assert_eq!(found.node(1).span().text(), "a");
+		assert_eq!(found.get(3).span().text(), "1 + 2");
+		assert_eq!(found.span().text(), "let a = 1 + 2");
+
+		found.set_done();
+		assert!(found.node(0).done() && found.node(2).done());
+		assert!(!found.get(3).into_iter().any(|x| x.done()));
+
+		let removed = found.remove();
+		assert_eq!(removed.len(), 6);
+		let node = Build::new("decl").child(found.node(1)).group(found.get(3)).node();
+		found.insert([node]);
+		assert_eq!(root.len(), 2);
+		assert_eq!(root.node(1), Some(node));
+		assert_eq!(node.span().text(), "a = 1 + 2");
+		assert_eq!(node.node(1).unwrap().span().text(), "1 + 2");
+
+		Queue::clear();
+		Node::clear_pending();
+		Ok(())
+	}
+
+	#[test]
+	fn rest_in_the_middle() -> Result<()> {
+		let _lock = init_test();
+
+		let root = parse("if a and b: c")?;
+		let pattern = Pattern::new([Match::word("if"), Match::Rest, Match::symbol(":"), Match::Rest]);
+		let found = pattern.match_at(root.node(0).unwrap()).unwrap();
+		assert_eq!(found.get(1).span().text(), "a and b");
+		assert_eq!(found.get(3).span().text(), "c");
+
+		// a rest with a single node is not marked as done
+		found.set_done();
+		assert!(found.node(0).done() && found.node(2).done());
+		assert!(!found.node(3).done());
+
+		let unit = Match::value::<()>();
+		let empty = Pattern::new([Match::Any, Match::Rest, Match::AnyWord, Match::Rest, unit]);
+		assert!(empty.match_at(root.node(0).unwrap()).is_none());
+
+		let parts = root.children().split(Match::word("and"));
+		let parts = parts.iter().map(|x| x.span().text()).collect::<Vec<_>>();
+		assert_eq!(parts, ["if a", "b: c"]);
+
+		let parts = parse("a,,b,")?.children().split(Match::symbol(","));
+		let spans = parts.iter().map(|x| x.span_or_pos()).collect::<Vec<_>>();
+		let spans = spans.iter().map(|x| (x.sta(), x.len())).collect::<Vec<_>>();
+		assert_eq!(spans, [(0, 1), (2, 0), (3, 1), (5, 0)]);
+
+		Queue::clear();
+		Node::clear_pending();
+		Ok(())
+	}
+}
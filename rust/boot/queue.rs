@@ -1,9 +1,44 @@
 use super::*;
 
+/// Stack size for the worker threads processing binds in parallel.
+const WORKER_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default limit for the number of binds executed by [`Queue::process`].
+pub const DEFAULT_MAX_STEPS: usize = 1_000_000;
+
+/// Number of times a bind can run over the same unchanged nodes before the
+/// evaluation is considered to be stuck in a cycle.
+const MAX_REPEATS: usize = 16;
+
+/// Number of recent binds kept to report the evals involved in a cycle.
+const HISTORY_LEN: usize = 64;
+
 #[derive(Default)]
 pub struct Queue {
 	pending_reindex: Mutex<VecDeque<&'static BindingMap>>,
 	queue: Mutex<BindQueue>,
+	jobs: AtomicUsize,
+	max_steps: AtomicUsize,
+	progress: Mutex<Progress>,
+}
+
+/// Tracks the binds executed by [`Queue::process`] to detect an evaluation
+/// that is not making progress.
+#[derive(Default)]
+struct Progress {
+	steps: usize,
+	runs: HashMap<Bind, Run>,
+	history: VecDeque<Bind>,
+}
+
+/// Last run of a bind, tracked by [`Queue::check_repeat`].
+struct Run {
+	/// Length of the log of added nodes for the bind's map at the run.
+	seen: usize,
+	/// Fingerprint of the nodes added since the previous run.
+	fingerprint: u64,
+	/// How many times in a row the fingerprint was the same.
+	repeats: usize,
 }
 
 impl Queue {
@@ -12,27 +47,52 @@ impl Queue {
 		QUEUE.get()
 	}
 
+	/// Sets the number of threads used by [`Queue::process`].
+	///
+	/// With more than one, independent binds are executed concurrently (see
+	/// [`Queue::process_batch`]). Tracing always uses a single thread.
+	pub fn set_jobs(jobs: usize) {
+		Self::get().jobs.store(jobs, Order::Relaxed);
+	}
+
+	/// Sets the maximum number of binds executed by [`Queue::process`]
+	/// before failing. Zero uses [`DEFAULT_MAX_STEPS`].
+	pub fn set_max_steps(max: usize) {
+		Self::get().max_steps.store(max, Order::Relaxed);
+	}
+
+	/// Executes binds until the queue is empty.
+	///
+	/// Fails if the number of binds executed exceeds the step limit (see
+	/// [`Queue::set_max_steps`]), or if the same bind keeps running over the
+	/// same unchanged nodes, since the evaluation would never finish.
 	pub fn process() -> Result<()> {
 		let queue = Self::get();
-		while queue.process_next()? {}
+		*queue.progress.lock().unwrap() = Progress::default();
+
+		let jobs = queue.jobs.load(Order::Relaxed);
+		if jobs > 1 && !Trace::is_enabled() {
+			while queue.process_batch(jobs)? {}
+		} else {
+			while queue.process_next()? {}
+		}
 
 		Ok(())
 	}
 
+	/// Discards any pending bindings, for example after an error.
+	pub fn clear() {
+		let queue = Self::get();
+		queue.pending_reindex.lock().unwrap().clear();
+		queue.queue.lock().unwrap().list.clear();
+	}
+
 	pub fn process_next(&self) -> Result<bool> {
-		let mut pending = self.pending_reindex.lock().unwrap();
-		for it in pending.drain(..) {
-			it.reindex();
-		}
-		drop(pending);
+		self.reindex();
 
 		let mut queue = self.queue.lock().unwrap();
-		let len = queue.len();
-		if len > 0 {
-			let bind = queue.list[0];
-			queue.swap(0, len - 1);
-			queue.list.truncate(len - 1);
-			queue.shift_down(0);
+		if let Some(bind) = queue.pop() {
+			self.start(&[bind])?;
 			bind.execute()?;
 			Ok(true)
 		} else {
@@ -40,6 +100,179 @@ impl Queue {
 		}
 	}
 
+	/// Executes the next batch of independent binds using up to `jobs`
+	/// threads.
+	///
+	/// A batch is taken from the front of the queue while the binds have
+	/// the same precedence and are for different sources or non-overlapping
+	/// spans, so their nodes are disjoint. Any changes are reindexed before
+	/// the next batch, as they would be between binds in [`Queue::process_next`].
+	///
+	/// If more than one bind fails, the error is the one for the first bind
+	/// in the queue order, matching the sequential output.
+	pub fn process_batch(&self, jobs: usize) -> Result<bool> {
+		self.reindex();
+
+		let mut batch: Vec<Bind> = Vec::new();
+		let mut queue = self.queue.lock().unwrap();
+		while let Some(next) = queue.list.first() {
+			if !batch.iter().all(|x| x.is_independent(next)) {
+				break;
+			}
+			batch.extend(queue.pop());
+		}
+		drop(queue);
+		self.start(&batch)?;
+
+		match batch.len() {
+			0 => return Ok(false),
+			1 => batch[0].execute()?,
+			_ => {
+				let batch = batch.into_iter().map(Shared).collect::<Vec<_>>();
+				let next = AtomicUsize::new(0);
+				let mut results = Vec::new();
+				std::thread::scope(|scope| {
+					let workers = (0..jobs.min(batch.len())).map(|_| {
+						let worker = std::thread::Builder::new().stack_size(WORKER_STACK_SIZE);
+						let worker = worker.spawn_scoped(scope, || {
+							let mut output = Vec::new();
+							loop {
+								let index = next.fetch_add(1, Order::Relaxed);
+								match batch.get(index) {
+									Some(bind) => output.push((index, Shared(bind.0.execute()))),
+									None => break output,
+								}
+							}
+						});
+						worker.expect("failed to start queue worker")
+					});
+
+					for it in workers.collect::<Vec<_>>() {
+						match it.join() {
+							Ok(output) => results.extend(output),
+							Err(panic) => std::panic::resume_unwind(panic),
+						}
+					}
+				});
+
+				results.sort_by_key(|x| x.0);
+				for (_, result) in results {
+					result.0?;
+				}
+			}
+		}
+
+		Ok(true)
+	}
+
+	/// Counts the binds about to be executed against the step limit.
+	fn start(&self, binds: &[Bind]) -> Result<()> {
+		let mut progress = self.progress.lock().unwrap();
+		progress.steps += binds.len();
+		for it in binds {
+			if progress.history.len() >= HISTORY_LEN {
+				progress.history.pop_front();
+			}
+			progress.history.push_back(*it);
+		}
+
+		let max = match self.max_steps.load(Order::Relaxed) {
+			0 => DEFAULT_MAX_STEPS,
+			max => max,
+		};
+		if progress.steps > max {
+			let mut err = err!("evaluation did not finish after {max} steps");
+			let mut recent: Vec<Bind> = Vec::new();
+			for it in progress.history.iter().rev() {
+				if recent.len() < 5 && !recent.contains(it) {
+					recent.push(*it);
+				}
+			}
+			for it in recent {
+				err = err.with_note(format!("recently executed {}", it.describe()));
+			}
+			return Err(err);
+		}
+		Ok(())
+	}
+
+	/// Checks that a bind is not running again because of the same nodes, in
+	/// the same state, as the previous times.
+	///
+	/// A bind only runs again once nodes are added to its map, so this only
+	/// looks at the nodes in the `log` of added nodes since its last run,
+	/// filtered by `visible`. A bind that keeps adding back the same nodes
+	/// without changing them is stuck.
+	pub(crate) fn check_repeat(&self, bind: Bind, log: &[Node], visible: impl Fn(Node) -> bool) -> Result<()> {
+		use std::hash::Hasher;
+
+		let mut progress = self.progress.lock().unwrap();
+		let seen = progress.runs.get(&bind).map(|x| x.seen).unwrap_or(0);
+
+		// a node that is bound again is added to the map again, so it can
+		// appear more than once
+		let mut unique = HashSet::new();
+		let added = log[seen..].iter().copied().filter(|x| visible(*x) && unique.insert(*x));
+		let nodes = added.collect::<Vec<_>>();
+
+		let mut hasher = std::hash::DefaultHasher::new();
+		for it in nodes.iter() {
+			(it, it.get_value(), it.done(), it.parent(), it.len()).hash(&mut hasher);
+		}
+		let fingerprint = hasher.finish();
+
+		let seen = log.len();
+		let run = progress.runs.entry(bind).or_insert(Run {
+			seen,
+			fingerprint,
+			repeats: 0,
+		});
+		run.seen = seen;
+		if run.fingerprint != fingerprint {
+			run.fingerprint = fingerprint;
+			run.repeats = 0;
+			return Ok(());
+		}
+
+		run.repeats += 1;
+		if run.repeats < MAX_REPEATS {
+			return Ok(());
+		}
+
+		let count = run.repeats + 1;
+		let desc = bind.describe();
+		let msg = format!("evaluation is not making progress, {desc} ran {count} times");
+		let mut err = match nodes.first() {
+			Some(node) => err!(@node => "{msg} over the same nodes"),
+			None => err!("{msg} without any new nodes"),
+		};
+		for it in nodes.iter().skip(1).take(3) {
+			err = err.with_label(it, "unchanged node");
+		}
+
+		// the other binds since the previous run are part of the cycle
+		let mut cycle: Vec<Bind> = Vec::new();
+		for it in progress.history.iter().rev().skip(1) {
+			if *it == bind {
+				break;
+			}
+			if !cycle.contains(it) {
+				cycle.push(*it);
+			}
+		}
+		for it in cycle.into_iter().rev() {
+			err = err.with_note(format!("cycle includes {}", it.describe()));
+		}
+		Err(err)
+	}
+
+	fn reindex(&self) {
+		let mut pending = self.pending_reindex.lock().unwrap();
+		for it in pending.drain(..) {
+			it.reindex();
+		}
+	}
+
 	pub(crate) fn queue_reindex(&self, map: &'static BindingMap) {
 		let mut pending = self.pending_reindex.lock().unwrap();
 		pending.push_back(map);
@@ -68,8 +301,37 @@ impl BindQueue {
 	pub fn swap(&mut self, a: usize, b: usize) {
 		self.list.swap(a, b);
 	}
+
+	pub fn pop(&mut self) -> Option<Bind> {
+		let len = self.len();
+		if len > 0 {
+			let bind = self.list[0];
+			self.swap(0, len - 1);
+			self.list.truncate(len - 1);
+			self.shift_down(0);
+			Some(bind)
+		} else {
+			None
+		}
+	}
 }
 
+/// Wrapper to share binds and their results with the worker threads.
+///
+/// Nodes and values are arena allocated and use atomics (or locks) for all
+/// changes, but are not marked as thread safe since most of the compiler
+/// is single threaded.
+struct Shared<T>(T);
+
+// SAFETY: binds in a batch are independent (see `Bind::is_independent`),
+// so the workers never change the same nodes.
+unsafe impl Send for Shared<Bind> {}
+unsafe impl Sync for Shared<Bind> {}
+
+// SAFETY: errors only reference arena allocated data, and are read by the
+// main thread once the workers have finished.
+unsafe impl Send for Shared<Result<()>> {}
+
 impl IsHeap for BindQueue {
 	fn heap_len(&self) -> usize {
 		self.len()
@@ -85,3 +347,57 @@ impl IsHeap for BindQueue {
 		a.cmp(&b).is_lt()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Eval that binds its nodes again every time, so it never finishes.
+	#[derive(Debug)]
+	struct Rebind;
+
+	impl Eval for Rebind {
+		fn precedence(&self) -> Precedence {
+			Precedence::First
+		}
+
+		fn execute(&self, nodes: &[Node]) -> Result<()> {
+			for it in nodes {
+				it.set_done(true);
+				it.set_done(false);
+			}
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn runaway_evaluation_is_detected() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("runaway", "print 1\nspin\n");
+		WORDS.get("spin").set_span(src.span(), Rebind);
+
+		let mut session = Session::new();
+		let err = session.execute(&[src]).unwrap_err().to_string();
+		assert!(err.contains("evaluation is not making progress, `Rebind` at runaway:1:1+13 ran 17 times"));
+		assert!(err.contains("--> runaway:2:1"), "{err}");
+		Ok(())
+	}
+
+	#[test]
+	fn step_limit() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("steps", "let x = 1 + 2\nprint x\n");
+		Queue::set_max_steps(3);
+		let result = Session::new().execute(&[src]);
+		Queue::set_max_steps(0);
+
+		let err = result.unwrap_err().to_string();
+		assert!(err.starts_with("error: evaluation did not finish after 3 steps"));
+		assert!(err.contains("= note: recently executed `DefaultLexer` at steps:1:1+22"));
+		Ok(())
+	}
+}
@@ -12,11 +12,65 @@ pub enum Error {
 
 #[derive(Clone)]
 pub struct ErrorData {
+	severity: Severity,
 	msg: Arc<str>,
 	span: Option<Span>,
+	labels: Arc<[Label]>,
+	notes: Arc<[Note]>,
 	file: Option<FileInfo>,
 }
 
+/// Severity for an [`Error`].
+///
+/// Only errors abort a compilation. Warnings and notes are reported through
+/// the [`Diagnostics`] for the compilation.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Severity {
+	#[default]
+	Error,
+	Warning,
+	Note,
+}
+
+impl Severity {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Severity::Error => "error",
+			Severity::Warning => "warning",
+			Severity::Note => "note",
+		}
+	}
+
+	fn color(&self) -> Color {
+		match self {
+			Severity::Error => RED,
+			Severity::Warning => YELLOW,
+			Severity::Note => CYAN,
+		}
+	}
+}
+
+impl Display for Severity {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+/// Secondary span for an error, with a label explaining how it relates to
+/// the error (e.g. a previous declaration).
+#[derive(Clone)]
+struct Label {
+	span: Span,
+	text: Arc<str>,
+}
+
+/// Trailing `note:` or `help:` line for an error.
+#[derive(Clone)]
+struct Note {
+	kind: &'static str,
+	text: Arc<str>,
+}
+
 #[derive(Clone)]
 pub struct ErrorList {
 	head: ErrorData,
@@ -27,8 +81,11 @@ impl Error {
 	#[inline(always)]
 	pub fn new<T: Display>(msg: T) -> Self {
 		let data = ErrorData {
+			severity: Severity::Error,
 			msg: format!("{msg}").into(),
 			span: None,
+			labels: Arc::new([]),
+			notes: Arc::new([]),
 			file: None,
 		};
 		Error::Single(data)
@@ -87,6 +144,70 @@ impl Error {
 		self
 	}
 
+	pub fn span(&self) -> Option<Span> {
+		self.data().span
+	}
+
+	pub fn severity(&self) -> Severity {
+		self.data().severity
+	}
+
+	pub fn with_severity(mut self, severity: Severity) -> Self {
+		self.data_mut().severity = severity;
+		self
+	}
+
+	/// Adds a secondary span to the error, with a label describing it.
+	pub fn with_label<T: HasSpan, U: Display>(mut self, at: T, label: U) -> Self {
+		let label = Label {
+			span: at.span(),
+			text: label.to_string().into(),
+		};
+		let data = self.data_mut();
+		data.labels = data.labels.iter().cloned().chain([label]).collect();
+		self
+	}
+
+	/// Adds a `note:` line with additional context for the error.
+	pub fn with_note<T: Display>(self, note: T) -> Self {
+		let note = Note {
+			kind: "note",
+			text: note.to_string().into(),
+		};
+		self.push_note(note)
+	}
+
+	/// Adds a `help:` line suggesting how to fix the error.
+	pub fn with_help<T: Display>(self, help: T) -> Self {
+		let note = Note {
+			kind: "help",
+			text: help.to_string().into(),
+		};
+		self.push_note(note)
+	}
+
+	fn push_note(mut self, note: Note) -> Self {
+		let data = self.data_mut();
+		data.notes = data.notes.iter().cloned().chain([note]).collect();
+		self
+	}
+
+	/// Splits an error list into its individual errors.
+	pub fn split(self) -> Vec<Error> {
+		let list = match self {
+			Error::Single(..) => return vec![self],
+			Error::List(list) => list,
+		};
+
+		let mut output = vec![Error::Single(list.head)];
+		let mut next = list.next;
+		while let Some(node) = next {
+			output.push(Error::Single(node.head.clone()));
+			next = node.next;
+		}
+		output
+	}
+
 	pub fn append(self, next: Error) -> Error {
 		let list = match next {
 			Error::Single(head) => ErrorList { head, next: None },
@@ -112,12 +233,64 @@ impl Error {
 		Error::List(list)
 	}
 
+	/// Writes the error, including the source snippets for its spans.
+	///
+	/// If `color` is set, the output is styled with terminal escape codes.
+	pub fn output(&self, out: &mut Writer, color: bool) -> Result<()> {
+		let paint = Paint(color);
+		match self {
+			Error::Single(data) => data.output(out, paint),
+			Error::List(list) => {
+				let mut next = Some(list);
+				while let Some(node) = next {
+					node.head.output(out, paint)?;
+					next = node.next;
+					if next.is_some() {
+						write!(out, "\n\n")?;
+					}
+				}
+				Ok(())
+			}
+		}
+	}
+
+	/// Writes the error as JSON, with one object per line for each error in
+	/// the list.
+	///
+	/// Each object has the following fields, with `null` for missing values:
+	///
+	/// - `severity`: one of `"error"`, `"warning"`, or `"note"`.
+	/// - `message`: the error message.
+	/// - `file`, `start`, `end`, `line`, `column`, `end_line`, `end_column`:
+	///   location for the error, with byte offsets and one-based positions.
+	/// - `related`: list of secondary spans, with a `message` and the same
+	///   location fields as the error.
+	/// - `notes`: list of `{"kind": "note" | "help", "message": ...}`.
+	pub fn output_json(&self, out: &mut Writer) -> Result<()> {
+		let mut next = match self {
+			Error::Single(data) => return data.output_json(out),
+			Error::List(list) => Some(list),
+		};
+		while let Some(node) = next {
+			node.head.output_json(out)?;
+			next = node.next;
+		}
+		Ok(())
+	}
+
 	fn data(&self) -> &ErrorData {
 		match self {
 			Error::Single(data) => data,
 			Error::List(list) => &list.head,
 		}
 	}
+
+	fn data_mut(&mut self) -> &mut ErrorData {
+		match self {
+			Error::Single(data) => data,
+			Error::List(list) => &mut list.head,
+		}
+	}
 }
 
 impl Debug for Error {
@@ -130,56 +303,269 @@ impl Debug for Error {
 impl Display for Error {
 	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
 		let out = &mut Writer::fmt(f);
-		match self {
-			Error::Single(data) => {
-				data.output(out, false)?;
-			}
-			Error::List(list) => {
-				write!(out, "Error: multiple errors:").map_err(|_| std::fmt::Error)?;
+		self.output(out, false)?;
+		Ok(())
+	}
+}
 
-				let out = &mut out.indented();
-				let mut next = Some(list);
-				while let Some(node) = next {
-					write!(out, "\n\n").map_err(|_| std::fmt::Error)?;
-					node.head.output(out, true).map_err(|_| std::fmt::Error)?;
-					next = node.next;
-				}
+/// Maximum number of source lines shown for a single span.
+const MAX_SNIPPET_LINES: usize = 4;
+
+impl ErrorData {
+	fn output(&self, f: &mut Writer, paint: Paint) -> Result<()> {
+		let mut msg = self.msg.lines();
+		paint.write(f, self.severity.color(), true, self.severity)?;
+		paint.write(f, DEFAULT, true, format!(": {}", msg.next().unwrap_or_default()))?;
+		for it in msg {
+			write!(f, "\n{it}")?;
+		}
+
+		// the primary span is marked with `^` and labels with `-`
+		let mut marks = Vec::new();
+		if let Some(span) = self.span.filter(|x| !x.is_empty()) {
+			marks.push(Mark {
+				span,
+				char: '^',
+				color: RED,
+				label: "",
+			});
+		}
+		for it in self.labels.iter().filter(|x| !x.span.is_empty()) {
+			marks.push(Mark {
+				span: it.span,
+				char: '-',
+				color: BLUE,
+				label: &it.text,
+			});
+		}
+
+		let width = marks
+			.iter()
+			.map(|x| x.span.location().line() + x.span.text().matches('\n').count())
+			.max()
+			.map(|line| line.to_string().len())
+			.unwrap_or(0);
+		let gutter = " ".repeat(width);
+
+		// snippets are grouped by source, starting with the primary span
+		let mut sources = Vec::new();
+		for it in marks.iter() {
+			if !sources.contains(&it.span.source()) {
+				sources.push(it.span.source());
 			}
 		}
+
+		for (n, src) in sources.into_iter().enumerate() {
+			let marks = marks.iter().filter(|x| x.span.source() == src).collect::<Vec<_>>();
+			let arrow = if n == 0 { "--> " } else { "::: " };
+			write!(f, "\n{gutter}")?;
+			paint.write(f, BLUE, true, arrow)?;
+			write!(f, "{}", marks[0].span.location())?;
+			paint.write(f, BLUE, true, format!("\n{gutter} |"))?;
+			output_snippet(f, paint, &gutter, &marks)?;
+		}
+
+		for note in self.notes.iter() {
+			write!(f, "\n{gutter}")?;
+			paint.write(f, BLUE, true, " = ")?;
+			paint.write(f, DEFAULT, true, format!("{}:", note.kind))?;
+			write!(f, " {}", note.text)?;
+		}
+
+		if let Some(info) = self.file {
+			write!(f, "\n{gutter}")?;
+			paint.write(f, BLUE, true, " = ")?;
+			paint.dim(f, format!("from {}:{}", info.file, info.line))?;
+		}
 		Ok(())
 	}
 }
 
 impl ErrorData {
-	fn output(&self, f: &mut Writer, list: bool) -> Result<()> {
-		let indent = if list {
-			write!(f, ">>> ")?;
-			"    "
-		} else {
-			write!(f, "Error: ")?;
-			"       "
-		};
+	fn output_json(&self, f: &mut Writer) -> Result<()> {
+		let severity = self.severity;
+		write!(f, "{{\"severity\":\"{severity}\",\"message\":{}", json_str(&self.msg))?;
+		write_json_span(f, self.span)?;
 
-		if self.msg.contains("\n") {
-			for (n, it) in self.msg.lines().enumerate() {
-				if n == 0 {
-					write!(f, "{it}\n")?;
-				} else {
-					write!(f, "\n{indent}||  {it}")?;
-				}
+		write!(f, ",\"related\":[")?;
+		for (n, it) in self.labels.iter().enumerate() {
+			let sep = if n > 0 { "," } else { "" };
+			write!(f, "{sep}{{\"message\":{}", json_str(&it.text))?;
+			write_json_span(f, Some(it.span))?;
+			write!(f, "}}")?;
+		}
+
+		write!(f, "],\"notes\":[")?;
+		for (n, it) in self.notes.iter().enumerate() {
+			let sep = if n > 0 { "," } else { "" };
+			let message = json_str(&it.text);
+			write!(f, "{sep}{{\"kind\":\"{}\",\"message\":{message}}}", it.kind)?;
+		}
+		writeln!(f, "]}}")?;
+		Ok(())
+	}
+}
+
+fn write_json_span(f: &mut Writer, span: Option<Span>) -> Result<()> {
+	let span = match span.filter(|x| !x.is_empty()) {
+		Some(span) => span,
+		None => {
+			let fields = ["file", "start", "end", "line", "column", "end_line", "end_column"];
+			for it in fields {
+				write!(f, ",\"{it}\":null")?;
 			}
-		} else {
-			write!(f, "{}", self.msg)?;
+			return Ok(());
+		}
+	};
+
+	let sta = span.location();
+	let mut end = sta;
+	end.skip_len(span.len());
+	write!(f, ",\"file\":{}", json_str(span.source().name()))?;
+	write!(f, ",\"start\":{},\"end\":{}", span.sta(), span.end())?;
+	write!(f, ",\"line\":{},\"column\":{}", sta.line(), sta.column())?;
+	write!(f, ",\"end_line\":{},\"end_column\":{}", end.line(), end.column())?;
+	Ok(())
+}
+
+fn json_str(text: &str) -> String {
+	let mut output = String::from("\"");
+	for chr in text.chars() {
+		match chr {
+			'"' => output.push_str("\\\""),
+			'\\' => output.push_str("\\\\"),
+			'\n' => output.push_str("\\n"),
+			'\r' => output.push_str("\\r"),
+			'\t' => output.push_str("\\t"),
+			chr if chr < ' ' => output.push_str(&format!("\\u{:04x}", chr as u32)),
+			chr => output.push(chr),
 		}
+	}
+	output.push('"');
+	output
+}
+
+/// Span to underline in an error snippet.
+struct Mark<'a> {
+	span: Span,
+	char: char,
+	color: Color,
+	label: &'a str,
+}
 
-		if let Some(span) = self.span {
-			write!(f, "\n\n{indent}@ {span}")?;
-			if let Some(context) = span.display_text(0) {
-				write!(f, "\n{indent}: {context}")?;
+/// Writes the source lines for the marks, each followed by the underline
+/// for the marks on that line.
+///
+/// The label for each mark is written after its last underline.
+fn output_snippet(f: &mut Writer, paint: Paint, gutter: &str, marks: &[&Mark]) -> Result<()> {
+	let mut lines = BTreeMap::<usize, (&str, Vec<_>)>::new();
+	for (index, mark) in marks.iter().enumerate() {
+		let snippet = snippet_lines(mark.span);
+		let count = snippet.len();
+		for (n, (line, text, range)) in snippet.into_iter().enumerate() {
+			let last = n == count - 1;
+			if count > MAX_SNIPPET_LINES && n >= MAX_SNIPPET_LINES - 1 && !last {
+				continue;
+			}
+
+			let len = text_width(&text[range.clone()]);
+			let entry = lines.entry(line).or_insert((text, Vec::new()));
+			if len > 0 || count == 1 {
+				entry.1.push((index, range, last));
 			}
 		}
-		if let Some(info) = self.file {
-			write!(f, "\n\n{indent}(from {}:{})", info.file, info.line)?;
+	}
+
+	let mut prev = None;
+	for (line, (text, underlines)) in lines {
+		if prev.is_some_and(|prev| prev + 1 < line) {
+			paint.write(f, BLUE, true, "\n...")?;
+		}
+		prev = Some(line);
+
+		let line = format!("{line:>width$} |", width = gutter.len());
+		paint.write(f, BLUE, true, format!("\n{line}"))?;
+		if !text.is_empty() {
+			write!(f, " {}", text.replace('\t', TAB))?;
+		}
+
+		for (index, range, last) in underlines {
+			let mark = marks[index];
+			let pad = text_width(&text[..range.start]);
+			let len = std::cmp::max(text_width(&text[range]), 1);
+			let marks = mark.char.to_string().repeat(len);
+			let label = if last && !mark.label.is_empty() {
+				format!(" {}", mark.label)
+			} else {
+				String::new()
+			};
+
+			paint.write(f, BLUE, true, format!("\n{gutter} |"))?;
+			write!(f, " {}", " ".repeat(pad))?;
+			paint.write(f, mark.color, true, format!("{marks}{label}"))?;
+		}
+	}
+	Ok(())
+}
+
+/// Tab expansion used for source lines in error snippets.
+const TAB: &str = "    ";
+
+fn text_width(text: &str) -> usize {
+	text.chars().map(|chr| if chr == '\t' { TAB.len() } else { 1 }).sum()
+}
+
+/// Returns the line number, text, and the range covered by the span for
+/// each source line in the span.
+fn snippet_lines(span: Span) -> Vec<(usize, &'static str, std::ops::Range<usize>)> {
+	let text = span.source().text();
+	let mut sta = text[..span.sta()].rfind('\n').map(|x| x + 1).unwrap_or(0);
+	let mut line = span.location().line();
+	let mut output = Vec::new();
+	loop {
+		let end = text[sta..].find('\n').map(|x| sta + x).unwrap_or(text.len());
+		let line_text = text[sta..end].trim_end_matches('\r');
+		let range_sta = std::cmp::min(std::cmp::max(span.sta(), sta) - sta, line_text.len());
+		let range_end = std::cmp::min(span.end(), sta + line_text.len()).saturating_sub(sta);
+		let range_end = std::cmp::max(range_sta, range_end);
+		output.push((line, line_text, range_sta..range_end));
+
+		if end + 1 >= span.end() || end >= text.len() {
+			break;
+		}
+		sta = end + 1;
+		line += 1;
+	}
+	output
+}
+
+/// Optional terminal styling for the error output.
+#[derive(Copy, Clone)]
+struct Paint(bool);
+
+impl Paint {
+	fn write<T: Display>(&self, f: &mut Writer, color: Color, bold: bool, text: T) -> Result<()> {
+		if self.0 {
+			term::reset(&mut *f)?;
+			if bold {
+				term::bold(&mut *f)?;
+			}
+			color.fg(&mut *f)?;
+		}
+		write!(f, "{text}")?;
+		if self.0 {
+			term::reset(&mut *f)?;
+		}
+		Ok(())
+	}
+
+	fn dim<T: Display>(&self, f: &mut Writer, text: T) -> Result<()> {
+		if self.0 {
+			term::dim(&mut *f)?;
+		}
+		write!(f, "{text}")?;
+		if self.0 {
+			term::reset(&mut *f)?;
 		}
 		Ok(())
 	}
@@ -245,6 +631,99 @@ mod macros {
 			Err(err!($($args)*))?
 		}
 	}
+
+	/// Push a warning with a formatted message to the [`Diagnostics`] for
+	/// the current compilation.
+	#[macro_export]
+	macro_rules! warning {
+		($($args:tt)+) => {
+			Diagnostics::push(err!($($args)*).with_severity(Severity::Warning))
+		}
+	}
 }
 
 pub use macros::*;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn error_snippets() -> Result<()> {
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("test.bit", "let x = 1\n\tx = 'abc'\n");
+		let err = Error::new("invalid assignment")
+			.at(Span::new(src, 15, 20))
+			.with_label(Span::new(src, 0, 5), "declared here")
+			.with_help("try this");
+
+		let expected = [
+			"error: invalid assignment",
+			" --> test.bit:2:9",
+			"  |",
+			"1 | let x = 1",
+			"  | ----- declared here",
+			"2 |     x = 'abc'",
+			"  |         ^^^^^",
+			"  = help: try this",
+		];
+		assert_eq!(err.to_string(), expected.join("\n"));
+		Ok(())
+	}
+
+	#[test]
+	fn error_snippets_with_multiple_lines() -> Result<()> {
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("test.bit", "a\nb\nc\nd\ne\nf\n");
+		let err = Error::new("some error").at(Span::new(src, 2, 11)).with_note("one\ntwo");
+
+		let expected = [
+			"error: some error",
+			" --> test.bit:2:1",
+			"  |",
+			"2 | b",
+			"  | ^",
+			"3 | c",
+			"  | ^",
+			"4 | d",
+			"  | ^",
+			"...",
+			"6 | f",
+			"  | ^",
+			"  = note: one\ntwo",
+		];
+		assert_eq!(err.to_string(), expected.join("\n"));
+		Ok(())
+	}
+
+	#[test]
+	fn error_json() -> Result<()> {
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("test.bit", "let x = 1\nx = 'abc'\n");
+		let a = Error::new("invalid \"assignment\"")
+			.at(Span::new(src, 14, 19))
+			.with_label(Span::new(src, 0, 5), "declared here")
+			.with_help("try this");
+		let b = Error::new("other error");
+
+		let mut output = String::new();
+		a.append(b).output_json(&mut Writer::fmt(&mut output))?;
+
+		let expected = [
+			concat!(
+				r#"{"severity":"error","message":"invalid \"assignment\"","#,
+				r#""file":"test.bit","start":14,"end":19,"line":2,"column":5,"end_line":2,"end_column":10,"#,
+				r#""related":[{"message":"declared here","#,
+				r#""file":"test.bit","start":0,"end":5,"line":1,"column":1,"end_line":1,"end_column":6}],"#,
+				r#""notes":[{"kind":"help","message":"try this"}]}"#,
+			),
+			concat!(
+				r#"{"severity":"error","message":"other error","#,
+				r#""file":null,"start":null,"end":null,"line":null,"column":null,"end_line":null,"end_column":null,"#,
+				r#""related":[],"notes":[]}"#,
+			),
+		];
+		assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+		Ok(())
+	}
+}
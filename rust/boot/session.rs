@@ -0,0 +1,446 @@
+use super::*;
+
+/// State shared by a sequence of programs, such as the entries in a REPL.
+///
+/// Top-level variables, functions, structs, and operators declared by a
+/// program are visible to the programs that follow, and variables keep
+/// their values between executions.
+#[derive(Default)]
+pub struct Session {
+	runtime: Runtime,
+	types: TypeChecker,
+	sources: Option<Arc<SourceMap>>,
+	defs: Vec<(Symbol, Definition)>,
+	operators: Vec<&'static OperatorDef>,
+	error_format: ErrorFormat,
+	warnings_as_errors: bool,
+	warnings: usize,
+	max_errors: Option<usize>,
+}
+
+/// Top-level declaration from a previous program in the session.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Definition {
+	Var(Var, Node),
+	Fn(Function),
+	Struct(Struct),
+}
+
+impl Session {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the source map used to load the modules imported by a program.
+	pub fn set_sources(&mut self, sources: Arc<SourceMap>) {
+		self.sources = Some(sources);
+	}
+
+	/// Sets the format used to report warnings.
+	pub fn set_error_format(&mut self, format: ErrorFormat) {
+		self.error_format = format;
+	}
+
+	/// If set, warnings fail the compilation as errors.
+	pub fn set_warnings_as_errors(&mut self, value: bool) {
+		self.warnings_as_errors = value;
+	}
+
+	/// Limits the number of errors returned for a program.
+	pub fn set_max_errors(&mut self, max: Option<usize>) {
+		self.max_errors = max;
+	}
+
+	/// Parses, compiles, and runs the input, returning the value for the
+	/// last expression in the program.
+	///
+	/// The top-level declarations are only added to the session if the
+	/// program runs successfully.
+	pub fn execute(&mut self, input: &[Source]) -> Result<Value> {
+		let program = self.load(input);
+		let result = self.solve(program).and_then(|_| self.compile(program));
+		let result = result.and_then(|code| self.run(&code));
+		if result.is_ok() {
+			for src in program.children() {
+				self.declare(src);
+			}
+		}
+		self.report_summary();
+		result
+	}
+
+	/// Creates the program node for the input, binding the declarations
+	/// from previous programs in the session.
+	pub fn load(&mut self, input: &[Source]) -> Node {
+		Diagnostics::clear();
+		let program = Node::new_at(Program, Span::empty());
+		Trace::set_program(program);
+		for it in input.iter().copied() {
+			for (name, def) in self.defs.iter() {
+				WORDS.get(*name).set_span(it.span(), BindPrevious(*def));
+			}
+			for op in self.operators.iter() {
+				bind_previous_operator(it, op);
+			}
+
+			let span = it.span();
+			let node = Node::new_at(it, span);
+			program.push_node(node);
+		}
+		Modules::reset(program.children(), self.sources.clone());
+		program
+	}
+
+	/// Parses the loaded program, checking that all nodes have been solved.
+	///
+	/// On errors, any pending state from the program is discarded, so the
+	/// session can be used to run other programs.
+	pub fn solve(&mut self, program: Node) -> Result<()> {
+		let result = Queue::process();
+		program.set_done(true);
+
+		let result = result.and_then(|_| Node::check_pending());
+		if result.is_err() {
+			Queue::clear();
+			Node::clear_pending();
+		}
+		self.check_diagnostics(result)
+	}
+
+	/// Generates and type checks the code for a solved program.
+	pub fn compile(&mut self, program: Node) -> Result<Code> {
+		let ctx = CodeContext::new();
+		let code = program.value().output_code(ctx, program);
+		let code = code.and_then(|code| {
+			code.check_type(&mut self.types)?;
+			Ok(code)
+		});
+		self.types.check_unused();
+
+		self.check_diagnostics(code)
+	}
+
+	/// Runs compiled code with the session runtime.
+	pub fn run(&mut self, code: &Code) -> Result<Value> {
+		code.execute(&mut self.runtime)
+	}
+
+	/// Reports the total number of warnings since the last summary.
+	pub fn report_summary(&mut self) {
+		let count = std::mem::take(&mut self.warnings);
+		if count > 0 && self.error_format == ErrorFormat::Text {
+			let s = if count == 1 { "" } else { "s" };
+			let summary = Error::new(format!("{count} warning{s} emitted"));
+			report(&summary.with_severity(Severity::Warning), self.error_format);
+		}
+	}
+
+	/// Reports the pending diagnostics for the current compilation.
+	///
+	/// Any errors in the diagnostics (or warnings, if those are treated as
+	/// errors) are returned together with the result error.
+	fn check_diagnostics<T>(&mut self, result: Result<T>) -> Result<T> {
+		let mut diagnostics = Diagnostics::take();
+		diagnostics.sort_by_key(|x| (x.span().is_none(), x.span()));
+
+		let mut errors = Vec::new();
+		for it in diagnostics {
+			let severity = it.severity();
+			if severity == Severity::Error || (self.warnings_as_errors && severity == Severity::Warning) {
+				errors.push(it.with_severity(Severity::Error));
+				continue;
+			}
+
+			if severity == Severity::Warning {
+				self.warnings += 1;
+			}
+			report(&it, self.error_format);
+		}
+
+		let errors = errors.into_iter().reduce(Error::append);
+		let errors = match (errors, result) {
+			(None, Ok(value)) => return Ok(value),
+			(None, Err(err)) => err,
+			(Some(errors), Ok(..)) => errors,
+			(Some(errors), Err(err)) => errors.append(err),
+		};
+
+		let mut errors = errors.split();
+		if let Some(max) = self.max_errors.filter(|&max| errors.len() > max) {
+			let cnt = errors.len() - max;
+			let s = if cnt > 1 { "s" } else { "" };
+			errors.truncate(max);
+			errors.push(err!("skipping remaining {cnt} error{s}"));
+		}
+		Err(errors.into_iter().reduce(Error::append).unwrap())
+	}
+
+	/// Adds the top-level declarations in the source to the session.
+	///
+	/// Modules loaded with `import` are not declared, their names are only
+	/// accessible through the module.
+	fn declare(&mut self, src: Node) {
+		if src.cast::<Source>().and_then(|x| Modules::imported(*x)).is_some() {
+			return;
+		}
+
+		for line in src.children() {
+			for it in line.children() {
+				if let Some(decl) = it.cast::<OperatorDecl>() {
+					self.operators.retain(|x| x.symbol != decl.0.symbol);
+					self.operators.push(decl.0);
+					continue;
+				}
+
+				let (name, def) = if let Some(decl) = it.cast::<Let>() {
+					(decl.0.name(), Definition::Var(decl.0, it))
+				} else if let Some(decl) = it.cast::<FnDecl>() {
+					(decl.0.name(), Definition::Fn(decl.0))
+				} else if let Some(decl) = it.cast::<StructDecl>() {
+					(decl.0.name(), Definition::Struct(decl.0))
+				} else {
+					continue;
+				};
+
+				self.defs.retain(|x| x.0 != name);
+				self.defs.push((name, def));
+			}
+		}
+	}
+}
+
+/// Binds names declared by a previous program in the session.
+///
+/// This runs after the bindings for the current program, so its own
+/// declarations take precedence.
+#[derive(Debug)]
+struct BindPrevious(Definition);
+
+impl Eval for BindPrevious {
+	fn precedence(&self) -> Precedence {
+		Precedence::SessionBinding
+	}
+
+	fn execute(&self, nodes: &[Node]) -> Result<()> {
+		let nodes = nodes.iter().copied().filter(|x| !x.done()).collect::<Vec<_>>();
+		match self.0 {
+			Definition::Var(var, decl) => BindVar(var, decl).execute(&nodes),
+			Definition::Fn(func) => BindFn(func).execute(&nodes),
+			Definition::Struct(typ) => BindStruct(typ).execute(&nodes),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn definitions_persist() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let mut session = Session::new();
+		let mut run = |name: &str, text: &str| {
+			let src = sources.from_string(name, text);
+			session.execute(&[src])
+		};
+
+		run("a", "let x = 20")?;
+		assert!(run("b", "x + ").is_err());
+		assert!(run("c", "x + 'a'").is_err());
+
+		let value = run("d", "fn add(a, b):\n\treturn a + b\nadd(x, 22)")?;
+		assert_eq!(value.cast::<i64>(), Some(&42));
+
+		let value = run("e", "let x = add(x, 1)\nx * 2")?;
+		assert_eq!(value.cast::<i64>(), Some(&42));
+
+		run("f", "struct P: x: i64, y: i64")?;
+		let value = run("g", "let p = P(x, 21)\np.x + p.y")?;
+		assert_eq!(value.cast::<i64>(), Some(&42));
+
+		run("h", "operator infixl 6 \"<>\" = add")?;
+		let value = run("i", "x <> 21")?;
+		assert_eq!(value.cast::<i64>(), Some(&42));
+
+		Ok(())
+	}
+
+	#[test]
+	fn failed_programs_declare_nothing() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let mut session = Session::new();
+		let mut run = |name: &str, text: &str| {
+			let src = sources.from_string(name, text);
+			session.execute(&[src])
+		};
+
+		run("a", "let x = 1")?;
+		assert!(run("b", "fn f():\n\treturn 2\nlet q = 1 / 0").is_err());
+
+		// names from the failed program are undefined, not uninitialized
+		for (name, text) in [("c", "print q"), ("d", "f()")] {
+			let err = run(name, text).unwrap_err().to_string();
+			assert!(err.contains("has not been solved"), "{err}");
+		}
+
+		let value = run("e", "let q = x + 41\nq")?;
+		assert_eq!(value.cast::<i64>(), Some(&42));
+		Ok(())
+	}
+
+	#[test]
+	fn unsolved_nodes_are_reported_separately() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("unsolved", "y\nx\n");
+		let err = Session::new().execute(&[src]).unwrap_err();
+
+		let mut output = String::new();
+		err.output_json(&mut Writer::fmt(&mut output))?;
+		let lines = output.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 2);
+		assert!(lines[0].contains(r#""message":"word(y) has not been solved","file":"unsolved","start":0"#));
+		assert!(lines[1].contains(r#""message":"word(x) has not been solved","file":"unsolved","start":2"#));
+		Ok(())
+	}
+
+	#[test]
+	fn unsolved_nodes_are_grouped_by_root() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("grouped", "a b a c\nd\n");
+		let tokens = core_lexer().tokenize(&mut Cursor::new(src))?;
+		let words = tokens.into_iter().filter(|x| matches!(x, Token::Word(..)));
+		let words = words.map(Node::new).collect::<Vec<_>>();
+
+		// the first line is nested inside an unsolved node
+		let root = Node::new_at(Program, words[0].span());
+		root.append_nodes(words[..4].iter().copied());
+		let result = Queue::process().and_then(|_| Node::check_pending());
+
+		let errors = result.unwrap_err().split();
+		let errors = errors.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+		assert_eq!(errors.len(), 2, "{errors:#?}");
+		assert!(errors[0].contains("4 nested nodes could not be solved because of this: word(a), word(b)"));
+		assert!(errors[1].starts_with("error: word(d) has not been solved"));
+
+		// unsolved nodes in the same line are a single report
+		let src = sources.from_string("line", "x y z w\nprint v\n");
+		let errors = Session::new().execute(&[src]).unwrap_err().split();
+		let errors = errors.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+		assert_eq!(errors.len(), 2, "{errors:#?}");
+		assert!(errors[0].starts_with("error: word(x) has not been solved"));
+		assert!(errors[0].contains("3 other nodes in the same expression could not be solved: word(y), word(z)"));
+		assert!(errors[1].starts_with("error: word(v) has not been solved"));
+
+		let mut session = Session::new();
+		session.set_max_errors(Some(1));
+		let src = sources.from_string("limited", "a\nb\na\nc\n");
+		let errors = session.execute(&[src]).unwrap_err().split();
+		let errors = errors.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+		assert_eq!(errors.len(), 2, "{errors:#?}");
+		assert!(errors[0].contains("also unsolved here"));
+		assert!(errors[1].starts_with("error: skipping remaining 2 errors"));
+		Ok(())
+	}
+
+	#[test]
+	fn parallel_queue_matches_sequential() -> Result<()> {
+		let _lock = init_test();
+
+		let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/basic");
+		let mut programs = Vec::new();
+		for entry in std::fs::read_dir(&dir)? {
+			let path = entry?.path();
+			if path.extension().is_some_and(|x| x == "bit") {
+				programs.push(vec![(path.display().to_string(), std::fs::read_to_string(&path)?)]);
+			}
+		}
+		programs.sort();
+		assert!(!programs.is_empty());
+
+		// sources are independent, so their binds can all run concurrently
+		let files = (0..8).map(|n| {
+			let text = format!("fn f{n}(a):\n\treturn a * {n}\nlet x{n} = f{n}({n}) + 1\nprint x{n}\n");
+			(format!("file-{n}"), text)
+		});
+		programs.push(files.collect());
+		let error = "let a = 1\nprint a + b\nprint (c\n";
+		programs.push(vec![("error".to_string(), error.to_string())]);
+
+		let solve = |program: &[(String, String)], jobs: usize| -> Result<String> {
+			Queue::set_jobs(jobs);
+			let sources = SourceMap::new(".")?;
+			let input = program.iter().map(|(name, text)| sources.from_string(name, text));
+			let input = input.collect::<Vec<_>>();
+
+			let mut session = Session::new();
+			let node = session.load(&input);
+			let result = session.solve(node).and_then(|_| session.compile(node));
+
+			let mut output = String::new();
+			node.write(&mut Writer::fmt(&mut output))?;
+			match result {
+				Ok(code) => output.push_str(&format!("\n{code:#?}")),
+				Err(err) => output.push_str(&format!("\n{err}")),
+			}
+			Ok(output)
+		};
+
+		for program in programs {
+			let sequential = solve(&program, 1);
+			let parallel = solve(&program, 4);
+			Queue::set_jobs(1);
+			assert_eq!(sequential?, parallel?, "{}", program[0].0);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn warnings() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let text = "fn f(a, b):\n\treturn a\n\tprint 1\nf(1, 2)\n";
+
+		let mut session = Session::new();
+		session.set_error_format(ErrorFormat::Json);
+		let value = session.execute(&[sources.from_string("warn-a", text)])?;
+		assert_eq!(value.cast::<i64>(), Some(&1));
+
+		let mut session = Session::new();
+		session.set_warnings_as_errors(true);
+		let err = session.execute(&[sources.from_string("warn-b", text)]).unwrap_err();
+
+		let mut output = String::new();
+		err.output_json(&mut Writer::fmt(&mut output))?;
+		let lines = output.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 2);
+		assert!(lines[0].starts_with(r#"{"severity":"error","message":"unused variable `b`""#));
+		assert!(lines[1].starts_with(r#"{"severity":"error","message":"unreachable code""#));
+		Ok(())
+	}
+
+	#[test]
+	fn lexical_errors_are_reported_together() -> Result<()> {
+		let _lock = init_test();
+
+		let sources = SourceMap::new(".")?;
+		let src = sources.from_string("lexical", "print 1 $ 2\nprint 'abc\nprint 3 @\n");
+		let err = Session::new().execute(&[src]).unwrap_err();
+
+		let errors = err.split();
+		let errors = errors.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+		assert_eq!(errors.len(), 3, "{errors:#?}");
+		assert!(errors[0].starts_with("error: invalid token `$`"));
+		assert!(errors[1].starts_with("error: unterminated string literal"));
+		assert!(errors[2].starts_with("error: invalid token `@`"));
+		Ok(())
+	}
+}
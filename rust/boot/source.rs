@@ -12,6 +12,10 @@ impl IsValue for Source {
 		SOURCES.add(node);
 	}
 
+	fn is_scope(&self) -> bool {
+		true
+	}
+
 	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
 		Code::sequence(ctx, node.children())
 	}
@@ -87,7 +87,7 @@ impl Span {
 			std::ops::Bound::Excluded(&n) => n,
 			std::ops::Bound::Unbounded => items.len(),
 		};
-		debug_assert!(end <= items.len() && sta < end);
+		debug_assert!(end <= items.len() && sta <= end);
 		if sta == 0 && end == 0 && items.len() == 0 {
 			parent.truncated(0)
 		} else if end > sta {
@@ -18,6 +18,9 @@ impl IsValue for Token {
 			Token::Float(..) => FLOAT.add(node),
 			Token::Literal(..) => LITERAL.add(node),
 			Token::Comment(..) => COMMENT.add(node),
+			// errors are reported by the lexer, so the node never needs to
+			// be solved
+			Token::Error(..) => node.set_done(true),
 		}
 	}
 
@@ -31,6 +34,7 @@ impl IsValue for Token {
 			Token::Float(..) => true,
 			Token::Literal(..) => true,
 			Token::Comment(..) => true,
+			Token::Error(..) => true,
 		};
 		if show_text {
 			if let Some(text) = self.span().display_text(16) {
@@ -100,6 +104,8 @@ pub enum Token {
 	Float(Span),
 	Literal(Span),
 	Comment(Span),
+	/// Invalid input, already reported as an error by the lexer.
+	Error(Span),
 }
 
 impl Token {
@@ -112,6 +118,7 @@ impl Token {
 			Token::Float(..) => Symbol::empty(),
 			Token::Literal(..) => Symbol::empty(),
 			Token::Comment(..) => Symbol::empty(),
+			Token::Error(..) => Symbol::empty(),
 		}
 	}
 }
@@ -134,6 +141,7 @@ impl Display for Token {
 			Token::Float(..) => write!(f, "float"),
 			Token::Literal(..) => write!(f, "literal"),
 			Token::Comment(..) => write!(f, "comment"),
+			Token::Error(..) => write!(f, "error"),
 		}
 	}
 }
@@ -148,6 +156,7 @@ impl HasSpan for Token {
 			Token::Float(span) => *span,
 			Token::Literal(span) => *span,
 			Token::Comment(span) => *span,
+			Token::Error(span) => *span,
 		}
 	}
 }
@@ -167,6 +176,26 @@ pub struct Symbols {
 	pub TRUE: Symbol,
 	pub FALSE: Symbol,
 	pub PRINT: Symbol,
+	pub LET: Symbol,
+	pub ASSIGN: Symbol,
+	pub IF: Symbol,
+	pub ELIF: Symbol,
+	pub ELSE: Symbol,
+	pub WHILE: Symbol,
+	pub FOR: Symbol,
+	pub IN: Symbol,
+	pub BREAK: Symbol,
+	pub CONTINUE: Symbol,
+	pub RANGE: Symbol,
+	pub RANGE_INCLUSIVE: Symbol,
+	pub FN: Symbol,
+	pub RETURN: Symbol,
+	pub OPERATOR: Symbol,
+	pub IMPORT: Symbol,
+	pub EXPORT: Symbol,
+	pub LEN: Symbol,
+	pub APPEND: Symbol,
+	pub STRUCT: Symbol,
 }
 
 impl Symbols {
@@ -185,6 +214,26 @@ impl Symbols {
 			TRUE: "true".into(),
 			FALSE: "false".into(),
 			PRINT: "print".into(),
+			LET: "let".into(),
+			ASSIGN: "=".into(),
+			IF: "if".into(),
+			ELIF: "elif".into(),
+			ELSE: "else".into(),
+			WHILE: "while".into(),
+			FOR: "for".into(),
+			IN: "in".into(),
+			BREAK: "break".into(),
+			CONTINUE: "continue".into(),
+			RANGE: "..".into(),
+			RANGE_INCLUSIVE: "..=".into(),
+			FN: "fn".into(),
+			RETURN: "return".into(),
+			OPERATOR: "operator".into(),
+			IMPORT: "import".into(),
+			EXPORT: "export".into(),
+			LEN: "len".into(),
+			APPEND: "append".into(),
+			STRUCT: "struct".into(),
 		});
 		SYMBOLS.get()
 	}
@@ -0,0 +1,242 @@
+use super::*;
+
+use std::io::BufRead;
+
+/// Debug trace for the binding queue.
+///
+/// When enabled, each [`Eval`] execution is logged to the standard error
+/// with the nodes it saw and how they changed. In step mode, the queue
+/// also pauses after each execution, reading commands from the standard
+/// input.
+///
+/// The trace can also record the evals that created or changed each node,
+/// without logging, for tools such as the HTML tree export.
+#[derive(Default)]
+pub struct Trace {
+	eval: AtomicBool,
+	step: AtomicBool,
+	record: AtomicBool,
+	count: AtomicUsize,
+	program: NodeCell,
+	current: Mutex<Option<String>>,
+	history: Mutex<HashMap<Node, Vec<String>>>,
+}
+
+/// State of a node before an eval, used to find what it changed.
+pub(crate) struct NodeState {
+	node: Node,
+	desc: String,
+	value: Value,
+	done: bool,
+	parent: Option<Node>,
+	len: usize,
+}
+
+const STEP_HELP: &str = "\
+commands:
+    <enter>, n   run the next eval
+    t            print the program tree
+    c            continue without stepping
+    q            stop the program
+";
+
+impl Trace {
+	fn get() -> &'static Self {
+		static TRACE: Init<Trace> = Init::default();
+		TRACE.get()
+	}
+
+	/// Enables logging for each eval executed by the queue.
+	pub fn set_eval(enabled: bool) {
+		Self::get().eval.store(enabled, Order::Relaxed);
+	}
+
+	/// Enables pausing after each eval executed by the queue. This also
+	/// enables the eval log.
+	pub fn set_step(enabled: bool) {
+		Self::get().step.store(enabled, Order::Relaxed);
+	}
+
+	/// Sets the program tree printed while stepping.
+	pub fn set_program(program: Node) {
+		Self::get().program.set(Some(program));
+	}
+
+	/// Enables recording the evals that create or change each node.
+	pub fn set_record(enabled: bool) {
+		Self::get().record.store(enabled, Order::Relaxed);
+	}
+
+	/// Returns the recorded evals for the node, in order, with a summary of
+	/// what each one did.
+	pub fn history(node: Node) -> Vec<String> {
+		let history = Self::get().history.lock().unwrap();
+		history.get(&node).cloned().unwrap_or_default()
+	}
+
+	pub(crate) fn is_enabled() -> bool {
+		let trace = Self::get();
+		trace.eval.load(Order::Relaxed) || trace.step.load(Order::Relaxed) || trace.record.load(Order::Relaxed)
+	}
+
+	/// Records a node created by the running eval, if any.
+	pub(crate) fn created(node: Node) {
+		let trace = Self::get();
+		if !trace.record.load(Order::Relaxed) {
+			return;
+		}
+
+		if let Some(name) = &*trace.current.lock().unwrap() {
+			let mut history = trace.history.lock().unwrap();
+			history.entry(node).or_default().push(format!("{name} (created)"));
+		}
+	}
+
+	/// Saves the state of the nodes given to an eval.
+	pub(crate) fn before(eval: &dyn Eval, nodes: &[Node]) -> Vec<NodeState> {
+		let trace = Self::get();
+		if trace.record.load(Order::Relaxed) {
+			*trace.current.lock().unwrap() = Some(eval_name(eval));
+		}
+
+		nodes
+			.iter()
+			.map(|&node| NodeState {
+				node,
+				desc: single_line(node.description()),
+				value: node.get_value(),
+				done: node.done(),
+				parent: node.parent(),
+				len: node.len(),
+			})
+			.collect()
+	}
+
+	/// Logs and records an eval execution, pausing afterwards if stepping.
+	pub(crate) fn after(eval: &dyn Eval, span: Span, nodes: Vec<NodeState>, result: &Result<()>) -> Result<()> {
+		let trace = Self::get();
+		let name = eval_name(eval);
+		let changes = nodes.iter().map(|x| x.changes()).collect::<Vec<_>>();
+
+		if trace.record.load(Order::Relaxed) {
+			*trace.current.lock().unwrap() = None;
+			let mut history = trace.history.lock().unwrap();
+			for (it, changes) in nodes.iter().zip(changes.iter()) {
+				if !changes.is_empty() {
+					let entry = format!("{name} ({})", changes.join(", "));
+					history.entry(it.node).or_default().push(entry);
+				}
+			}
+		}
+
+		let step = trace.step.load(Order::Relaxed);
+		if !step && !trace.eval.load(Order::Relaxed) {
+			return Ok(());
+		}
+
+		let count = trace.count.fetch_add(1, Order::Relaxed) + 1;
+		let mut out = Writer::stderr();
+		let precedence = eval.precedence();
+		writeln!(out, "[eval {count}] {name} ({precedence:?}) at {span}")?;
+		if nodes.is_empty() {
+			writeln!(out, "    (no nodes)")?;
+		}
+
+		for (it, changes) in nodes.iter().zip(changes) {
+			write!(out, "    {} at {}", it.desc, it.node.span())?;
+			if !changes.is_empty() {
+				write!(out, " => {}", changes.join(", "))?;
+			}
+			writeln!(out)?;
+		}
+
+		if let Err(err) = result {
+			let err = err.to_string();
+			writeln!(out, "    {}", err.lines().next().unwrap_or_default())?;
+		}
+
+		if step {
+			trace.pause()?;
+		}
+		Ok(())
+	}
+
+	fn pause(&self) -> Result<()> {
+		let stdin = std::io::stdin();
+		loop {
+			eprint!("step> ");
+			let mut line = String::new();
+			if stdin.lock().read_line(&mut line)? == 0 {
+				// stop stepping at the end of the input
+				self.step.store(false, Order::Relaxed);
+				return Ok(());
+			}
+
+			match line.trim() {
+				"" | "n" => return Ok(()),
+				"c" => {
+					self.step.store(false, Order::Relaxed);
+					return Ok(());
+				}
+				"t" => {
+					let mut out = Writer::stderr();
+					match self.program.get() {
+						Some(program) => program.write(&mut out)?,
+						None => write!(out, "no program loaded")?,
+					}
+					writeln!(out)?;
+				}
+				"q" => raise!("stopped while stepping through the evaluation"),
+				_ => eprint!("{STEP_HELP}"),
+			}
+		}
+	}
+}
+
+impl NodeState {
+	/// Describes how the node changed since the state was saved.
+	fn changes(&self) -> Vec<String> {
+		let node = self.node;
+		let mut changes = Vec::new();
+		if node.get_value() != self.value {
+			changes.push(single_line(node.description()));
+		}
+		if node.done() != self.done {
+			changes.push(if node.done() { "done" } else { "not done" }.to_string());
+		}
+		if node.parent() != self.parent {
+			let moved = if node.parent().is_none() { "removed" } else { "moved" };
+			changes.push(moved.to_string());
+		}
+		if node.len() != self.len {
+			changes.push(format!("{} children", node.len()));
+		}
+		changes
+	}
+}
+
+pub(crate) fn eval_name(eval: &dyn Eval) -> String {
+	single_line(format!("{eval:?}"))
+}
+
+/// Collapses a multi-line description (e.g. for a collection) to its first
+/// and last lines.
+pub(crate) fn single_line(desc: String) -> String {
+	let mut lines = desc.lines();
+	match (lines.next(), lines.last()) {
+		(Some(first), Some(last)) => format!("{first}…{}", last.trim()),
+		_ => desc,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn single_line_descriptions() {
+		assert_eq!(single_line("word(x)".to_string()), "word(x)");
+		assert_eq!(single_line("Raw(\n    [0] word(x)\n)".to_string()), "Raw(…)");
+		assert_eq!(single_line("Group {\n    [0] int\n    }".to_string()), "Group {…}");
+	}
+}
@@ -26,6 +26,10 @@ pub trait IsValue: 'static + Debug {
 		false
 	}
 
+	fn is_scope(&self) -> bool {
+		false
+	}
+
 	fn output_code(&self, ctx: CodeContext, node: Node) -> Result<Code> {
 		let _ = ctx;
 		let mut msg = String::new();
@@ -45,7 +49,7 @@ impl<T: IsValue> From<T> for Value {
 	}
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Value {
 	data: NonNull<ValueData<()>>,
 }